@@ -0,0 +1,161 @@
+use clap::{Parser, Subcommand};
+use pwd_dl_zkp_client::client::{Client, Error, Session};
+use std::io::BufRead;
+use std::process::exit;
+
+#[derive(Debug, Parser)]
+#[clap(
+    name = "pwd-dl-zkp",
+    about = "Register and log in against a pwd-dl-zkp server"
+)]
+struct Cli {
+    /// Base URL of the server
+    #[clap(long, env = "ZKP_URL", default_value = "http://localhost:8000")]
+    url: String,
+
+    /// Print the whole session transcript as JSON instead of the session token
+    #[clap(long)]
+    transcript: bool,
+
+    /// TOTP code, for users who enabled the second factor
+    #[clap(long, env = "ZKP_TOTP")]
+    totp: Option<String>,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Register the public key derived from the password
+    Register {
+        username: String,
+        /// One-time code handed out by support staff, to replace the key of a re-enrolled user
+        #[clap(long)]
+        enrollment: Option<String>,
+    },
+    /// Prove the knowledge of the password and get an authenticated session
+    Login { username: String },
+    /// Log in, then enable TOTP: prints the URI to add to an authenticator app, then reads a code
+    /// generated by it from the next line of stdin
+    Totp { username: String },
+    /// Log in, then replace the key with the one derived from a new password, revoking every
+    /// session of the user
+    Passwd { username: String },
+    /// Log in, then enroll the key derived from the password in a set the user is a member of
+    Enroll { username: String, set: String },
+    /// Anonymously prove to be one of the members of a set and print the token scoped to it
+    Member { set: String },
+}
+
+fn read_line() -> String {
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line).unwrap();
+
+    line.trim_end_matches(&['\r', '\n'][..]).to_string()
+}
+
+/// The password is taken from `ZKP_PASSWORD`, or else from the first line of stdin, so that it
+/// doesn't end up in the shell history.
+fn read_password() -> String {
+    std::env::var("ZKP_PASSWORD").unwrap_or_else(|_| read_line())
+}
+
+/// The new password is taken from `ZKP_NEW_PASSWORD`, or else from the next line of stdin.
+fn read_new_password() -> String {
+    std::env::var("ZKP_NEW_PASSWORD").unwrap_or_else(|_| read_line())
+}
+
+fn print_session(session: &Session, transcript: bool) {
+    if transcript {
+        println!("{}", serde_json::to_string_pretty(session).unwrap());
+    } else {
+        println!("{}", session.token.as_ref().unwrap_or(&session.client_id));
+    }
+}
+
+fn fail(err: Error) -> ! {
+    eprintln!("error: {}", err);
+    exit(1);
+}
+
+/// Logs in and returns the session token, for the commands acting on behalf of the user.
+async fn login_token(
+    client: &Client,
+    username: &str,
+    password: &str,
+    totp: Option<&str>,
+) -> String {
+    let session = client
+        .login_with_totp(username, password, totp)
+        .await
+        .unwrap_or_else(|err| fail(err));
+
+    session
+        .token
+        .ok_or(Error::Unauthenticated)
+        .unwrap_or_else(|err| fail(err))
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let client = Client::new(cli.url.as_str());
+    let password = read_password();
+    let totp = cli.totp.as_deref();
+
+    match &cli.command {
+        Command::Register {
+            username,
+            enrollment,
+        } => {
+            let session = client
+                .register_with_enrollment(username, password.as_str(), enrollment.as_deref())
+                .await;
+            print_session(&session.unwrap_or_else(|err| fail(err)), cli.transcript);
+        }
+        Command::Login { username } => {
+            let session = client
+                .login_with_totp(username, password.as_str(), totp)
+                .await;
+            print_session(&session.unwrap_or_else(|err| fail(err)), cli.transcript);
+        }
+        Command::Totp { username } => {
+            let token = login_token(&client, username, password.as_str(), totp).await;
+
+            let enrollment = client
+                .enroll_totp(token.as_str())
+                .await
+                .unwrap_or_else(|err| fail(err));
+            println!("{}", enrollment.uri);
+
+            client
+                .confirm_totp(token.as_str(), read_line().as_str())
+                .await
+                .unwrap_or_else(|err| fail(err));
+        }
+        Command::Passwd { username } => {
+            let new_password = read_new_password();
+
+            client
+                .change_password(username, password.as_str(), new_password.as_str(), totp)
+                .await
+                .unwrap_or_else(|err| fail(err));
+        }
+        Command::Enroll { username, set } => {
+            let session = client
+                .login_with_totp(username, password.as_str(), totp)
+                .await
+                .unwrap_or_else(|err| fail(err));
+
+            client
+                .enroll(set, &session, password.as_str())
+                .await
+                .unwrap_or_else(|err| fail(err));
+        }
+        Command::Member { set } => {
+            let token = client.prove_membership(set, password.as_str()).await;
+            println!("{}", token.unwrap_or_else(|err| fail(err)));
+        }
+    }
+}
@@ -0,0 +1,68 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use num_bigint::{BigInt, Sign};
+use once_cell::sync::Lazy;
+use openssl::bn::BigNum;
+use pwd_dl_zkp_core::core::Choice;
+use pwd_dl_zkp_core::protocol::Version;
+use pwd_dl_zkp_core::vectors::{Vectors, V1};
+use pwd_dl_zkp_peggy::peggy::Peggy;
+use pwd_dl_zkp_victor::victor::Victor;
+
+/// Wider secrets and nonces only make the exponentiations slower.
+const MAX_BYTES: usize = 128;
+
+static VECTORS: Lazy<Vectors> = Lazy::new(|| serde_json::from_str(V1).unwrap());
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    /// Whether the rounds follow the rules of V2 rather than V1.
+    v2: bool,
+    group: u8,
+    x: Vec<u8>,
+    r: Vec<u8>,
+    xrmp: bool,
+}
+
+fn to_bigint(bytes: &[u8]) -> BigInt {
+    BigInt::from_bytes_be(Sign::Plus, &bytes[..bytes.len().min(MAX_BYTES)])
+}
+
+fn to_bignum(n: &BigInt) -> BigNum {
+    BigNum::from_slice(&n.to_bytes_be().1).unwrap()
+}
+
+// Whatever the secret and the nonce, the verifier accepts the rounds of the prover, and only them.
+fuzz_target!(|input: Input| {
+    let group = &VECTORS.groups[input.group as usize % VECTORS.groups.len()];
+    let p = BigInt::parse_bytes(group.p.as_bytes(), 16).unwrap();
+    let g = BigInt::parse_bytes(group.g.as_bytes(), 16).unwrap();
+    let (x, r) = (to_bigint(&input.x), to_bigint(&input.r));
+    let choice = if input.xrmp { Choice::XRMP } else { Choice::R };
+    let version = if input.v2 { Version::V2 } else { Version::V1 };
+
+    let y = Peggy::public_key(&x, &g, &p).unwrap();
+    let c = Peggy::calc_c(&r, &g, &p).unwrap();
+    let q = Peggy::response_modulus(version, &g, &p);
+    let res = Peggy::calc_choice(&choice, &x, &r, &q).unwrap();
+
+    let victor = Victor::new();
+    let verify = |res: &BigInt| {
+        victor
+            .verify(
+                version,
+                &choice,
+                &to_bignum(res),
+                &to_bignum(&c),
+                &to_bignum(&y),
+                &to_bignum(&g),
+                &to_bignum(&p),
+            )
+            .unwrap()
+    };
+
+    assert!(verify(&res));
+    assert!(!verify(&(&res + 1)));
+});
@@ -0,0 +1,87 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use num_bigint::BigUint;
+use once_cell::sync::Lazy;
+use openssl::bn::BigNum;
+use pwd_dl_zkp_core::core::Choice;
+use pwd_dl_zkp_core::encoding::{self, Width};
+use pwd_dl_zkp_core::protocol::Version;
+use pwd_dl_zkp_core::vectors::{Vectors, V2};
+use pwd_dl_zkp_fe::native;
+use pwd_dl_zkp_victor::victor::Victor;
+
+/// Wider numbers only make the exponentiations slower.
+const MAX_BYTES: usize = 128;
+
+static VECTORS: Lazy<Vectors> = Lazy::new(|| serde_json::from_str(V2).unwrap());
+
+#[derive(Arbitrary, Debug)]
+enum Input {
+    /// Whatever the strings, the exports fail rather than panic.
+    Strings {
+        x: String,
+        g: String,
+        r: String,
+        p: String,
+        xrmp: bool,
+    },
+    /// In the groups of the test vectors, whatever the secret and the nonce, the verifier accepts
+    /// the rounds of the exports.
+    Agreement {
+        group: u8,
+        x: Vec<u8>,
+        r: Vec<u8>,
+        xrmp: bool,
+    },
+}
+
+fn choice(xrmp: bool) -> Choice {
+    if xrmp {
+        Choice::XRMP
+    } else {
+        Choice::R
+    }
+}
+
+fuzz_target!(|input: Input| match input {
+    Input::Strings { x, g, r, p, xrmp } => {
+        if [&x, &g, &r, &p].iter().any(|s| s.len() > 2 * MAX_BYTES) {
+            return;
+        }
+
+        let _ = native::public_key(&x, &g, &p);
+        let _ = native::gen_r(&g, &p);
+        let _ = native::calc_c(&r, &g, &p);
+        let _ = native::calc_choice(&choice(xrmp), &x, &r, &g, &p);
+    }
+    Input::Agreement { group, x, r, xrmp } => {
+        let group = &VECTORS.groups[group as usize % VECTORS.groups.len()];
+        let width = Width::of_modulus(&encoding::from_hex(&group.p).unwrap());
+        let x = BigUint::from_bytes_be(&x[..x.len().min(MAX_BYTES)]).to_str_radix(16);
+        let r = match width.to_hex(&r) {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+        let choice = choice(xrmp);
+
+        let y = native::public_key(&x, &group.g, &group.p).unwrap();
+        let c = native::calc_c(&r, &group.g, &group.p).unwrap();
+        let res = native::calc_choice(&choice, &x, &r, &group.g, &group.p).unwrap();
+
+        let hex = |n: &str| BigNum::from_hex_str(n).unwrap();
+        let valid = Victor::new()
+            .verify(
+                Version::V2,
+                &choice,
+                &hex(&res),
+                &hex(&c),
+                &hex(&y),
+                &hex(&group.g),
+                &hex(&group.p),
+            )
+            .unwrap();
+        assert!(valid);
+    }
+});
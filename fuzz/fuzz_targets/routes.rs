@@ -0,0 +1,240 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use ntex::http::header::HeaderValue;
+use ntex::web::test::{call_service, init_service, TestRequest};
+use ntex::web::{self, App};
+use once_cell::sync::Lazy;
+use pwd_dl_zkp::client_data::ClientData;
+use pwd_dl_zkp::config::CONFIG;
+use pwd_dl_zkp::database::{DB, USERS};
+use pwd_dl_zkp::router::routes;
+use pwd_dl_zkp::user_data::UserData;
+use pwd_dl_zkp_core::encoding;
+use pwd_dl_zkp_core::protocol::Version;
+use serde_json::{Map, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The registered user, in the smallest group of the test vectors so that random numbers of the
+/// right width are often numbers of the group.
+const USERNAME: &str = "fuzz";
+const P: &str = "17";
+const G: &str = "05";
+const Y: &str = "03";
+
+const MAX_CALLS: usize = 16;
+
+static SESSIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Points the database to a scratch directory before anything opens it, and registers the user.
+static SETUP: Lazy<()> = Lazy::new(|| {
+    let db_path = std::env::temp_dir().join(format!("pwd-dl-zkp-fuzz-{}", std::process::id()));
+    std::env::set_var("ZKP_DB_PATH", db_path);
+
+    let user = UserData::new(Version::V1, P, G, Y);
+    USERS
+        .insert(USERNAME, serde_json::to_string(&user).unwrap().as_str())
+        .unwrap();
+});
+
+#[derive(Arbitrary, Debug)]
+enum Route {
+    Handshake { versions: Option<String> },
+    PublicKey,
+    PickChoice,
+    Verify,
+    Authenticated,
+    Password,
+    Totp,
+    TotpConfirm,
+    TotpVerify,
+    MembershipKey,
+    MembershipCommit,
+    MembershipRespond,
+}
+
+#[derive(Arbitrary, Debug)]
+enum ClientId {
+    Session,
+    Missing,
+    Arbitrary(Vec<u8>),
+}
+
+/// The fields of every request struct.
+#[derive(Arbitrary, Debug)]
+enum Field {
+    Y,
+    C,
+    Res,
+    T,
+    S,
+    E,
+    Keys,
+    Code,
+    ProofId,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Number {
+    Hex(Vec<u8>),
+    HexSeq(Vec<Vec<u8>>),
+    Text(String),
+    Integer(i64),
+}
+
+#[derive(Arbitrary, Debug)]
+enum Body {
+    Raw(Vec<u8>),
+    Fields(Vec<(Field, Number)>),
+}
+
+#[derive(Arbitrary, Debug)]
+struct Call {
+    route: Route,
+    client_id: ClientId,
+    body: Body,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    /// Whether the session logs in as the user, or still has to register a key.
+    registered: bool,
+    calls: Vec<Call>,
+}
+
+/// Every byte percent encoded, whatever it is.
+fn query(s: &str) -> String {
+    s.bytes().map(|b| format!("%{:02x}", b)).collect()
+}
+
+impl Field {
+    fn name(&self) -> &'static str {
+        match self {
+            Field::Y => "y",
+            Field::C => "c",
+            Field::Res => "res",
+            Field::T => "t",
+            Field::S => "s",
+            Field::E => "e",
+            Field::Keys => "keys",
+            Field::Code => "code",
+            Field::ProofId => "proofId",
+        }
+    }
+}
+
+impl Number {
+    fn to_json(&self) -> Value {
+        match self {
+            Number::Hex(n) => Value::from(encoding::to_hex(n)),
+            Number::HexSeq(ns) => ns.iter().map(|n| encoding::to_hex(n)).collect(),
+            Number::Text(s) => Value::from(s.as_str()),
+            Number::Integer(i) => Value::from(*i),
+        }
+    }
+}
+
+impl Body {
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Body::Raw(bytes) => bytes.clone(),
+            Body::Fields(fields) => {
+                let fields: Map<String, Value> = fields
+                    .iter()
+                    .map(|(field, n)| (field.name().to_string(), n.to_json()))
+                    .collect();
+
+                serde_json::to_vec(&fields).unwrap()
+            }
+        }
+    }
+}
+
+impl Call {
+    fn request(&self, session: &str) -> TestRequest {
+        let req = match &self.route {
+            Route::Handshake { versions } => {
+                let mut uri = format!("/handshake?username={}", USERNAME);
+                if let Some(versions) = versions {
+                    uri.push_str(&format!("&versions={}", query(versions)));
+                }
+
+                TestRequest::get().uri(&uri)
+            }
+            Route::Authenticated => TestRequest::get().uri("/authenticated"),
+            route => {
+                let path = match route {
+                    Route::PublicKey => "/public-key",
+                    Route::PickChoice => "/pick-choice",
+                    Route::Verify => "/verify",
+                    Route::Password => "/password",
+                    Route::Totp => "/totp",
+                    Route::TotpConfirm => "/totp/confirm",
+                    Route::TotpVerify => "/totp/verify",
+                    Route::MembershipKey => "/membership/fuzz/key",
+                    Route::MembershipCommit => "/membership/fuzz/commit",
+                    Route::MembershipRespond => "/membership/fuzz/respond",
+                    Route::Handshake { .. } | Route::Authenticated => unreachable!(),
+                };
+
+                TestRequest::post()
+                    .uri(path)
+                    .header("content-type", "application/json")
+                    .set_payload(self.body.to_bytes())
+            }
+        };
+
+        let client_id = match &self.client_id {
+            ClientId::Session => HeaderValue::from_str(session).ok(),
+            ClientId::Missing => None,
+            ClientId::Arbitrary(bytes) => HeaderValue::from_bytes(bytes).ok(),
+        };
+        match client_id {
+            Some(client_id) => req.header("x-client-id", client_id),
+            None => req,
+        }
+    }
+}
+
+/// A session as the handshake of the user leaves it, without generating a group.
+fn session(registered: bool) -> String {
+    let client_id = format!("fuzz-{}", SESSIONS.fetch_add(1, Ordering::Relaxed));
+
+    let mut data = ClientData::new();
+    data.p = Some(P.to_string());
+    data.g = Some(G.to_string());
+    if registered {
+        data.username = Some(USERNAME.to_string());
+        data.y = Some(Y.to_string());
+    }
+
+    DB.lock()
+        .unwrap()
+        .insert(
+            client_id.as_str(),
+            serde_json::to_string(&data).unwrap().as_str(),
+        )
+        .unwrap();
+
+    client_id
+}
+
+// Whatever the requests, in whatever order, the routes answer rather than panic.
+fuzz_target!(|input: Input| {
+    Lazy::force(&SETUP);
+
+    ntex::rt::System::new("fuzz").block_on(async {
+        let app = init_service(
+            App::new()
+                .app_state(web::types::JsonConfig::default().limit(CONFIG.json_limit))
+                .service(routes()),
+        )
+        .await;
+        let session = session(input.registered);
+
+        for call in input.calls.iter().take(MAX_CALLS) {
+            call_service(&app, call.request(&session).to_request()).await;
+        }
+    });
+});
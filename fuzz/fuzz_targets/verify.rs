@@ -0,0 +1,95 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use once_cell::sync::Lazy;
+use openssl::bn::BigNum;
+use pwd_dl_zkp_core::core::Choice;
+use pwd_dl_zkp_core::protocol::Version;
+use pwd_dl_zkp_core::vectors::{Vectors, V1};
+use pwd_dl_zkp_victor::victor::{Round, Victor};
+use rand::thread_rng;
+
+/// Wider numbers only make the exponentiations slower.
+const MAX_BYTES: usize = 128;
+
+const MAX_ROUNDS: usize = 16;
+
+static VECTORS: Lazy<Vectors> = Lazy::new(|| serde_json::from_str(V1).unwrap());
+
+#[derive(Arbitrary, Debug)]
+enum Group {
+    /// One of the groups of the test vectors, where the verdicts of batches have to agree with
+    /// those of single rounds.
+    Vector(u8),
+    Arbitrary {
+        p: Vec<u8>,
+        g: Vec<u8>,
+    },
+}
+
+#[derive(Arbitrary, Debug)]
+struct RoundBytes {
+    xrmp: bool,
+    res: Vec<u8>,
+    c: Vec<u8>,
+    y: Vec<u8>,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    /// Whether the rounds are checked by the rules of V2 rather than V1.
+    v2: bool,
+    group: Group,
+    rounds: Vec<RoundBytes>,
+}
+
+fn number(bytes: &[u8]) -> BigNum {
+    BigNum::from_slice(&bytes[..bytes.len().min(MAX_BYTES)]).unwrap()
+}
+
+fuzz_target!(|input: Input| {
+    let victor = Victor::new();
+    let version = if input.v2 { Version::V2 } else { Version::V1 };
+
+    let (p, g, vector) = match &input.group {
+        Group::Vector(i) => {
+            let group = &VECTORS.groups[*i as usize % VECTORS.groups.len()];
+            let hex = |n: &str| BigNum::from_hex_str(n).unwrap();
+
+            (hex(&group.p), hex(&group.g), true)
+        }
+        Group::Arbitrary { p, g } => (number(p), number(g), false),
+    };
+
+    let numbers: Vec<_> = input
+        .rounds
+        .iter()
+        .take(MAX_ROUNDS)
+        .map(|round| {
+            let choice = if round.xrmp { Choice::XRMP } else { Choice::R };
+
+            (
+                choice,
+                number(&round.res),
+                number(&round.c),
+                number(&round.y),
+            )
+        })
+        .collect();
+
+    let verdicts: Result<Vec<bool>, _> = numbers
+        .iter()
+        .map(|(choice, res, c, y)| victor.verify(version, choice, res, c, y, &g, &p))
+        .collect();
+
+    let rounds: Vec<_> = numbers
+        .iter()
+        .map(|(choice, res, c, y)| Round { choice, res, c, y })
+        .collect();
+    let batch = victor.verify_batch(version, &rounds, &g, &p, &mut thread_rng());
+
+    if vector {
+        assert_eq!(batch.unwrap(), verdicts.unwrap());
+    }
+});
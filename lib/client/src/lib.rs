@@ -0,0 +1,757 @@
+pub mod client {
+    use num_bigint::{BigInt, ParseBigIntError};
+    use pwd_dl_zkp_core::core::Choice;
+    use pwd_dl_zkp_core::encoding::{self, EncodingError, Width};
+    use pwd_dl_zkp_core::protocol::{Version, CHALLENGE_BITS};
+    use pwd_dl_zkp_peggy::peggy::{Peggy, Prover, ProverError, Secret};
+    use rand::rngs::OsRng;
+    use serde::{Deserialize, Serialize};
+    use std::fmt::{Display, Formatter};
+    use zeroize::Zeroizing;
+
+    /// Upper bound on the rounds of a single login, the server stops at the first failed one.
+    const MAX_ROUNDS: usize = 100;
+
+    #[derive(Debug)]
+    pub enum Error {
+        Http(reqwest::Error),
+        Server(String),
+        Number(ParseBigIntError),
+        Encoding(EncodingError),
+        Prover(ProverError),
+        EmptyPassword,
+        AlreadyRegistered,
+        NotRegistered,
+        TooManyRounds,
+        Unauthenticated,
+        NotMember,
+        TotpRequired,
+        InvalidTotp,
+    }
+
+    impl Display for Error {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Error::Http(err) => write!(f, "http error: {}", err),
+                Error::Server(err) => write!(f, "server error: {}", err),
+                Error::Number(err) => write!(f, "invalid number: {}", err),
+                Error::Encoding(err) => write!(f, "invalid encoding: {}", err),
+                Error::Prover(err) => write!(f, "prover error: {}", err),
+                Error::EmptyPassword => write!(f, "the password is empty"),
+                Error::AlreadyRegistered => write!(f, "the user is already registered"),
+                Error::NotRegistered => write!(f, "the user is not registered"),
+                Error::TooManyRounds => write!(f, "too many rounds without authenticating"),
+                Error::Unauthenticated => write!(f, "the server did not authenticate the user"),
+                Error::NotMember => write!(f, "the password's key is not enrolled in the set"),
+                Error::TotpRequired => write!(f, "the user requires a TOTP code"),
+                Error::InvalidTotp => write!(f, "the server refused the TOTP code"),
+            }
+        }
+    }
+
+    impl std::error::Error for Error {}
+
+    impl From<reqwest::Error> for Error {
+        fn from(err: reqwest::Error) -> Self {
+            Error::Http(err)
+        }
+    }
+
+    impl From<ParseBigIntError> for Error {
+        fn from(err: ParseBigIntError) -> Self {
+            Error::Number(err)
+        }
+    }
+
+    impl From<EncodingError> for Error {
+        fn from(err: EncodingError) -> Self {
+            Error::Encoding(err)
+        }
+    }
+
+    impl From<ProverError> for Error {
+        fn from(err: ProverError) -> Self {
+            Error::Prover(err)
+        }
+    }
+
+    #[derive(Clone, Debug, Serialize)]
+    pub struct Round {
+        pub c: String,
+        pub choice: Choice,
+        pub res: String,
+        pub valid: bool,
+    }
+
+    /// A session opened by `/handshake`, `client_id` is what the server expects in `x-client-id`.
+    /// Serialized, it is the transcript of everything exchanged with the server, numbers in the
+    /// canonical hex encoding.
+    #[derive(Clone, Debug, Serialize)]
+    pub struct Session {
+        pub client_id: String,
+        pub version: Version,
+        pub p: String,
+        pub g: String,
+        pub y: String,
+        pub rounds: Vec<Round>,
+        /// The server's hash of the proof transcript, set once authenticated.
+        pub transcript: Option<String>,
+        /// Bearer token bound to the session and its transcript, set once authenticated.
+        pub token: Option<String>,
+    }
+
+    /// A TOTP secret drawn by the server, to be added to an authenticator app and confirmed.
+    #[derive(Clone, Debug, Deserialize, Serialize)]
+    pub struct TotpEnrollment {
+        pub secret: String,
+        pub uri: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct HandshakeResponse {
+        #[serde(rename = "clientId")]
+        client_id: String,
+        version: Version,
+        #[serde(with = "encoding::hex")]
+        p: Vec<u8>,
+        #[serde(with = "encoding::hex")]
+        g: Vec<u8>,
+        registered: bool,
+        #[serde(default)]
+        totp: bool,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct ErrorResponse {
+        err: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct PickChoiceResponse {
+        choice: Choice,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct VerifyResponse {
+        cont: bool,
+        valid: bool,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct TotpVerifyResponse {
+        valid: bool,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct AuthenticatedResponse {
+        auth: bool,
+        transcript: String,
+        token: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct MembershipSetResponse {
+        #[serde(with = "encoding::hex")]
+        p: Vec<u8>,
+        #[serde(with = "encoding::hex")]
+        g: Vec<u8>,
+        #[serde(with = "encoding::hex::seq")]
+        keys: Vec<Vec<u8>>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct MembershipCommitResponse {
+        #[serde(rename = "proofId")]
+        proof_id: String,
+        #[serde(with = "encoding::hex")]
+        challenge: Vec<u8>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct MembershipRespondResponse {
+        valid: bool,
+        token: Option<String>,
+    }
+
+    /// Turns a password into the secret exponent the same way the web app does: the hex code of
+    /// every UTF-16 unit, concatenated.
+    pub fn password_to_secret(password: &str) -> Result<Secret, Error> {
+        if password.is_empty() {
+            return Err(Error::EmptyPassword);
+        }
+
+        // Sized up front, the buffers are never reallocated and leave no copies behind.
+        let mut digits = Zeroizing::new(Vec::with_capacity(4 * password.len()));
+        for unit in password.encode_utf16() {
+            let width = (u16::BITS - unit.leading_zeros()).div_ceil(4).max(1);
+            digits.extend((0..width).rev().map(|i| (unit >> (4 * i) & 0xf) as u8));
+        }
+
+        let mut bytes = Zeroizing::new(vec![0u8; digits.len().div_ceil(2)]);
+        let last = bytes.len() - 1;
+        for (i, digit) in digits.iter().rev().enumerate() {
+            bytes[last - i / 2] |= digit << (4 * (i % 2));
+        }
+
+        Ok(Secret::new(bytes))
+    }
+
+    /// The group of a session or of a set, and the width its numbers are encoded with.
+    struct Group {
+        p: BigInt,
+        g: BigInt,
+        width: Width,
+    }
+
+    impl Group {
+        fn from_wire(p: &[u8], g: &[u8]) -> Result<Self, Error> {
+            let width = Width::of_modulus(p);
+
+            Ok(Group {
+                p: Peggy::from_wire(p, width)?,
+                g: Peggy::from_wire(g, width)?,
+                width,
+            })
+        }
+
+        fn of(session: &Session) -> Result<Self, Error> {
+            Self::from_wire(
+                &encoding::from_hex(&session.p)?,
+                &encoding::from_hex(&session.g)?,
+            )
+        }
+
+        fn to_wire(&self, n: &BigInt) -> Result<Vec<u8>, Error> {
+            Ok(Peggy::to_wire(n, self.width)?)
+        }
+
+        fn to_wire_all(&self, numbers: &[BigInt]) -> Result<Vec<Vec<u8>>, Error> {
+            numbers.iter().map(|n| self.to_wire(n)).collect()
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct Client {
+        base_url: String,
+        http: reqwest::Client,
+    }
+
+    impl Client {
+        pub fn new(base_url: &str) -> Self {
+            Self {
+                base_url: base_url.trim_end_matches('/').to_string(),
+                http: reqwest::Client::new(),
+            }
+        }
+
+        /// Opens a session for `username` and registers the public key derived from `password`.
+        pub async fn register(&self, username: &str, password: &str) -> Result<Session, Error> {
+            self.register_with_enrollment(username, password, None)
+                .await
+        }
+
+        /// Like `register`, also sending the one-time `enrollment` code support staff handed
+        /// out to replace the key of a re-enrolled user.
+        pub async fn register_with_enrollment(
+            &self,
+            username: &str,
+            password: &str,
+            enrollment: Option<&str>,
+        ) -> Result<Session, Error> {
+            let x = password_to_secret(password)?;
+
+            let handshake = self.handshake(username).await?;
+            if handshake.registered {
+                return Err(Error::AlreadyRegistered);
+            }
+
+            let session = Self::session(handshake, &x)?;
+
+            self.post(
+                "/public-key",
+                &session,
+                &PublicKeyRequest {
+                    y: encoding::from_hex(&session.y)?,
+                    enrollment: enrollment.map(String::from),
+                },
+            )
+            .await?;
+
+            Ok(session)
+        }
+
+        /// Opens a session for `username` and proves the knowledge of `password` until the server
+        /// is satisfied. The returned session is authenticated.
+        pub async fn login(&self, username: &str, password: &str) -> Result<Session, Error> {
+            self.login_with_totp(username, password, None).await
+        }
+
+        /// Like `login`, also sending `totp` when the user enabled the second factor.
+        pub async fn login_with_totp(
+            &self,
+            username: &str,
+            password: &str,
+            totp: Option<&str>,
+        ) -> Result<Session, Error> {
+            let x = password_to_secret(password)?;
+
+            let handshake = self.handshake(username).await?;
+            if !handshake.registered {
+                return Err(Error::NotRegistered);
+            }
+            let totp = match (handshake.totp, totp) {
+                (true, None) => return Err(Error::TotpRequired),
+                (true, Some(totp)) => Some(totp),
+                (false, _) => None,
+            };
+
+            let mut session = Self::session(handshake, &x)?;
+
+            let group = Group::of(&session)?;
+            let mut prover = Prover::new(session.version, &x, &group.g, &group.p)?;
+
+            loop {
+                if session.rounds.len() == MAX_ROUNDS {
+                    return Err(Error::TooManyRounds);
+                }
+
+                let c = group.to_wire(&prover.commit(&mut OsRng))?;
+
+                let PickChoiceResponse { choice } = self
+                    .post(
+                        "/pick-choice",
+                        &session,
+                        &PickChoiceRequest { c: c.clone() },
+                    )
+                    .await?
+                    .json()
+                    .await?;
+
+                let res = group.to_wire(&prover.respond(&choice)?)?;
+
+                let VerifyResponse { cont, valid } = self
+                    .post("/verify", &session, &VerifyRequest { res: res.clone() })
+                    .await?
+                    .json()
+                    .await?;
+
+                session.rounds.push(Round {
+                    c: encoding::to_hex(&c),
+                    choice,
+                    res: encoding::to_hex(&res),
+                    valid,
+                });
+
+                if !cont {
+                    break;
+                }
+            }
+
+            // The server only takes a code once the password is proven.
+            let proven = session.rounds.iter().all(|round| round.valid);
+            if let Some(code) = totp.filter(|_| proven) {
+                let TotpVerifyResponse { valid } = self
+                    .post(
+                        "/totp/verify",
+                        &session,
+                        &TotpCodeRequest {
+                            code: code.to_string(),
+                        },
+                    )
+                    .await?
+                    .json()
+                    .await?;
+
+                if !valid {
+                    return Err(Error::InvalidTotp);
+                }
+            }
+
+            let AuthenticatedResponse {
+                auth,
+                transcript,
+                token,
+            } = Self::check(
+                self.http
+                    .get(format!("{}/authenticated", self.base_url))
+                    .header("x-client-id", session.client_id.as_str())
+                    .send()
+                    .await?,
+            )
+            .await?
+            .json()
+            .await?;
+
+            if !auth {
+                return Err(Error::Unauthenticated);
+            }
+
+            session.transcript = Some(transcript);
+            session.token = token;
+
+            Ok(session)
+        }
+
+        /// Enrolls in `set` the key derived from `password` in the set's group, proving the
+        /// knowledge of it within `session`, the authenticated login of a member of the set.
+        pub async fn enroll(
+            &self,
+            set: &str,
+            session: &Session,
+            password: &str,
+        ) -> Result<(), Error> {
+            let (transcript, token) = match (&session.transcript, &session.token) {
+                (Some(transcript), Some(token)) => (transcript, token),
+                _ => return Err(Error::Unauthenticated),
+            };
+
+            let x = password_to_secret(password)?;
+
+            let MembershipSetResponse { p, g, .. } = self.membership_set(set).await?;
+            let group = Group::from_wire(&p, &g)?;
+            let prover = Prover::new(session.version, &x, &group.g, &group.p)?;
+            let y = prover.public_key();
+
+            let context = format!("{}.{}.{}", session.client_id, transcript, set);
+            let proof = prover.prove_knowledge(context.as_bytes(), &mut OsRng);
+
+            Self::check(
+                self.http
+                    .post(format!("{}/membership/{}/key", self.base_url, set))
+                    .bearer_auth(token)
+                    .json(&MembershipKeyRequest {
+                        y: group.to_wire(&y)?,
+                        t: group.to_wire(&proof.t)?,
+                        s: group.to_wire(&proof.s)?,
+                    })
+                    .send()
+                    .await?,
+            )
+            .await?;
+
+            Ok(())
+        }
+
+        /// Draws a TOTP secret for the user `token` was issued to, it has to be confirmed with
+        /// `confirm_totp` before logins ask for codes.
+        pub async fn enroll_totp(&self, token: &str) -> Result<TotpEnrollment, Error> {
+            Ok(Self::check(
+                self.http
+                    .post(format!("{}/totp", self.base_url))
+                    .bearer_auth(token)
+                    .send()
+                    .await?,
+            )
+            .await?
+            .json()
+            .await?)
+        }
+
+        /// Enables the TOTP secret drawn by `enroll_totp`, `code` being generated from it.
+        pub async fn confirm_totp(&self, token: &str, code: &str) -> Result<(), Error> {
+            Self::check(
+                self.http
+                    .post(format!("{}/totp/confirm", self.base_url))
+                    .bearer_auth(token)
+                    .json(&TotpCodeRequest {
+                        code: code.to_string(),
+                    })
+                    .send()
+                    .await?,
+            )
+            .await?;
+
+            Ok(())
+        }
+
+        /// Logs in with `old`, then replaces the key with the one derived from `new`, proving the
+        /// knowledge of both within the authenticated session. The server revokes every session
+        /// of the user, and the keys enrolled in sets have to be enrolled again.
+        pub async fn change_password(
+            &self,
+            username: &str,
+            old: &str,
+            new: &str,
+            totp: Option<&str>,
+        ) -> Result<(), Error> {
+            let session = self.login_with_totp(username, old, totp).await?;
+            let (transcript, token) = match (&session.transcript, &session.token) {
+                (Some(transcript), Some(token)) => (transcript, token),
+                _ => return Err(Error::Unauthenticated),
+            };
+
+            let group = Group::of(&session)?;
+            let prover = Prover::new(
+                session.version,
+                &password_to_secret(new)?,
+                &group.g,
+                &group.p,
+            )?;
+            let y = group.to_wire(&prover.public_key())?;
+
+            let context = format!("{}.{}", session.client_id, transcript);
+            let proof = prover.prove_knowledge(context.as_bytes(), &mut OsRng);
+
+            let old_prover = Prover::new(
+                session.version,
+                &password_to_secret(old)?,
+                &group.g,
+                &group.p,
+            )?;
+            let old_context = format!("{}.{}", context, encoding::to_hex(&y));
+            let old_proof = old_prover.prove_knowledge(old_context.as_bytes(), &mut OsRng);
+
+            Self::check(
+                self.http
+                    .post(format!("{}/password", self.base_url))
+                    .bearer_auth(token)
+                    .json(&PasswordRequest {
+                        y,
+                        t: group.to_wire(&proof.t)?,
+                        s: group.to_wire(&proof.s)?,
+                        old_t: group.to_wire(&old_proof.t)?,
+                        old_s: group.to_wire(&old_proof.s)?,
+                    })
+                    .send()
+                    .await?,
+            )
+            .await?;
+
+            Ok(())
+        }
+
+        /// Proves that `password` is behind one of the keys enrolled in `set`, without telling
+        /// which, and returns the token scoped to the set.
+        pub async fn prove_membership(&self, set: &str, password: &str) -> Result<String, Error> {
+            let x = password_to_secret(password)?;
+
+            let MembershipSetResponse { p, g, keys } = self.membership_set(set).await?;
+            let group = Group::from_wire(&p, &g)?;
+            let (g, p) = (&group.g, &group.p);
+            let ys = keys
+                .iter()
+                .map(|y| Peggy::from_wire(y, group.width))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let y = x.pow(g, p);
+            let k = ys
+                .iter()
+                .position(|y_i| y_i == &y)
+                .ok_or(Error::NotMember)?;
+
+            let commitment = Peggy::calc_or_c(k, &ys, g, p, &mut OsRng)?;
+            let MembershipCommitResponse {
+                proof_id,
+                challenge,
+            } = Self::check(
+                self.http
+                    .post(format!("{}/membership/{}/commit", self.base_url, set))
+                    .json(&MembershipCommitRequest {
+                        t: group.to_wire_all(&commitment.t)?,
+                        keys,
+                    })
+                    .send()
+                    .await?,
+            )
+            .await?
+            .json()
+            .await?;
+
+            let challenge = Peggy::from_wire(&challenge, Width(CHALLENGE_BITS / 8))?;
+            let response = Peggy::calc_or_res(commitment, &challenge, &x, p)?;
+
+            let MembershipRespondResponse { valid, token } = Self::check(
+                self.http
+                    .post(format!("{}/membership/{}/respond", self.base_url, set))
+                    .json(&MembershipRespondRequest {
+                        proof_id,
+                        e: response
+                            .e
+                            .iter()
+                            .map(|e| Peggy::to_wire(e, Width(CHALLENGE_BITS / 8)))
+                            .collect::<Result<_, _>>()?,
+                        s: group.to_wire_all(&response.s)?,
+                    })
+                    .send()
+                    .await?,
+            )
+            .await?
+            .json()
+            .await?;
+
+            match (valid, token) {
+                (true, Some(token)) => Ok(token),
+                _ => Err(Error::Unauthenticated),
+            }
+        }
+
+        async fn membership_set(&self, set: &str) -> Result<MembershipSetResponse, Error> {
+            Ok(Self::check(
+                self.http
+                    .get(format!("{}/membership/{}", self.base_url, set))
+                    .send()
+                    .await?,
+            )
+            .await?
+            .json()
+            .await?)
+        }
+
+        fn session(handshake: HandshakeResponse, x: &Secret) -> Result<Session, Error> {
+            let group = Group::from_wire(&handshake.p, &handshake.g)?;
+            let y = x.pow(&group.g, &group.p);
+
+            Ok(Session {
+                client_id: handshake.client_id,
+                version: handshake.version,
+                p: encoding::to_hex(&handshake.p),
+                g: encoding::to_hex(&handshake.g),
+                y: encoding::to_hex(&group.to_wire(&y)?),
+                rounds: vec![],
+                transcript: None,
+                token: None,
+            })
+        }
+
+        async fn handshake(&self, username: &str) -> Result<HandshakeResponse, Error> {
+            let versions = Version::SUPPORTED
+                .iter()
+                .map(Version::as_str)
+                .collect::<Vec<_>>()
+                .join(",");
+
+            Ok(Self::check(
+                self.http
+                    .get(format!("{}/handshake", self.base_url))
+                    .query(&[("username", username), ("versions", versions.as_str())])
+                    .send()
+                    .await?,
+            )
+            .await?
+            .json()
+            .await?)
+        }
+
+        async fn post<T: Serialize>(
+            &self,
+            path: &str,
+            session: &Session,
+            body: &T,
+        ) -> Result<reqwest::Response, Error> {
+            Self::check(
+                self.http
+                    .post(format!("{}{}", self.base_url, path))
+                    .header("x-client-id", session.client_id.as_str())
+                    .json(body)
+                    .send()
+                    .await?,
+            )
+            .await
+        }
+
+        async fn check(res: reqwest::Response) -> Result<reqwest::Response, Error> {
+            if res.status().is_success() {
+                return Ok(res);
+            }
+
+            let status = res.status();
+            match res.json::<ErrorResponse>().await {
+                Ok(ErrorResponse { err }) => Err(Error::Server(err)),
+                Err(_) => Err(Error::Server(status.to_string())),
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    struct PublicKeyRequest {
+        #[serde(with = "encoding::hex")]
+        y: Vec<u8>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        enrollment: Option<String>,
+    }
+
+    #[derive(Debug, Serialize)]
+    struct PickChoiceRequest {
+        #[serde(with = "encoding::hex")]
+        c: Vec<u8>,
+    }
+
+    #[derive(Debug, Serialize)]
+    struct VerifyRequest {
+        #[serde(with = "encoding::hex")]
+        res: Vec<u8>,
+    }
+
+    #[derive(Debug, Serialize)]
+    struct TotpCodeRequest {
+        code: String,
+    }
+
+    #[derive(Debug, Serialize)]
+    struct PasswordRequest {
+        #[serde(with = "encoding::hex")]
+        y: Vec<u8>,
+        #[serde(with = "encoding::hex")]
+        t: Vec<u8>,
+        #[serde(with = "encoding::hex")]
+        s: Vec<u8>,
+        #[serde(rename = "oldT", with = "encoding::hex")]
+        old_t: Vec<u8>,
+        #[serde(rename = "oldS", with = "encoding::hex")]
+        old_s: Vec<u8>,
+    }
+
+    #[derive(Debug, Serialize)]
+    struct MembershipKeyRequest {
+        #[serde(with = "encoding::hex")]
+        y: Vec<u8>,
+        #[serde(with = "encoding::hex")]
+        t: Vec<u8>,
+        #[serde(with = "encoding::hex")]
+        s: Vec<u8>,
+    }
+
+    #[derive(Debug, Serialize)]
+    struct MembershipCommitRequest {
+        #[serde(with = "encoding::hex::seq")]
+        keys: Vec<Vec<u8>>,
+        #[serde(with = "encoding::hex::seq")]
+        t: Vec<Vec<u8>>,
+    }
+
+    #[derive(Debug, Serialize)]
+    struct MembershipRespondRequest {
+        #[serde(rename = "proofId")]
+        proof_id: String,
+        #[serde(with = "encoding::hex::seq")]
+        e: Vec<Vec<u8>>,
+        #[serde(with = "encoding::hex::seq")]
+        s: Vec<Vec<u8>>,
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use num_traits::Num;
+        use pwd_dl_zkp_core::vectors::{Vectors, V1};
+
+        #[test]
+        fn vectors() {
+            let vectors: Vectors = serde_json::from_str(V1).unwrap();
+
+            let number = |n: &str| BigInt::from_str_radix(n, 16).unwrap();
+
+            for key in &vectors.keys {
+                let group = vectors.group(&key.group).unwrap();
+                let (p, g) = (number(&group.p), number(&group.g));
+
+                let x = password_to_secret(&key.password).unwrap();
+                assert_eq!(x.pow(&g, &p), number(&key.y), "{}", key.password);
+                assert_eq!(
+                    Peggy::public_key(&number(&key.x), &g, &p).unwrap(),
+                    number(&key.y)
+                );
+            }
+        }
+    }
+}
@@ -2,8 +2,516 @@ pub mod core {
     use serde::{Deserialize, Serialize};
 
     #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+    #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
     pub enum Choice {
         XRMP,
         R,
     }
 }
+
+pub mod protocol {
+    use serde::{Deserialize, Serialize};
+    use std::fmt::{Display, Formatter};
+    use std::str::FromStr;
+
+    /// Size of the challenges of Chaum–Pedersen and OR proofs, OR challenges add up modulo
+    /// 2^CHALLENGE_BITS.
+    pub const CHALLENGE_BITS: usize = 128;
+
+    /// Domain separation label of the Fiat–Shamir challenge of Chaum–Pedersen proofs.
+    pub const CHAUM_PEDERSEN_LABEL: &str = "pwd-dl-zkp/chaum-pedersen";
+
+    /// Domain separation label of the Fiat–Shamir Schnorr proofs of knowledge of a key.
+    pub const SCHNORR_LABEL: &str = "pwd-dl-zkp/schnorr";
+
+    /// A protocol version fixes the wire encoding, the challenge space and the group,
+    /// a session speaks the same version from the handshake to the end.
+    #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+    #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+    pub enum Version {
+        /// Numbers hex encoded as wide as `p` (see `encoding`), `Choice` challenges and a freshly
+        /// generated safe prime group.
+        #[serde(rename = "pwd-dl-zkp/1")]
+        V1,
+        /// As V1, but nonces are drawn from [1, q) and responses reduced mod q, the order of `g`:
+        /// the verifier rejects any response that isn't.
+        #[serde(rename = "pwd-dl-zkp/2")]
+        V2,
+    }
+
+    impl Version {
+        /// Every version this build can speak, most preferred first.
+        pub const SUPPORTED: &'static [Version] = &[Version::V2, Version::V1];
+
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                Version::V1 => "pwd-dl-zkp/1",
+                Version::V2 => "pwd-dl-zkp/2",
+            }
+        }
+
+        /// Picks the first of the `offered` versions, in the peer's order of preference,
+        /// that is supported; unknown versions are skipped.
+        pub fn negotiate<I, S>(offered: I) -> Option<Version>
+        where
+            I: IntoIterator<Item = S>,
+            S: AsRef<str>,
+        {
+            offered
+                .into_iter()
+                .filter_map(|version| version.as_ref().trim().parse().ok())
+                .find(|version| Self::SUPPORTED.contains(version))
+        }
+    }
+
+    /// Peers that predate negotiation never offer a version and speak V1.
+    impl Default for Version {
+        fn default() -> Self {
+            Version::V1
+        }
+    }
+
+    impl Display for Version {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", self.as_str())
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub struct UnknownVersion(pub String);
+
+    impl FromStr for Version {
+        type Err = UnknownVersion;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Version::SUPPORTED
+                .iter()
+                .find(|version| version.as_str() == s)
+                .copied()
+                .ok_or_else(|| UnknownVersion(s.to_string()))
+        }
+    }
+}
+
+/// Canonical wire encoding of numbers: big endian bytes, as wide as the modulus of their group
+/// whatever their value, as lowercase hex or unpadded base64url. Parsing is strict, so that
+/// every number has exactly one encoding and transcripts can be hashed on either side.
+pub mod encoding {
+    use std::fmt::{Display, Formatter};
+
+    const HEX_ALPHABET: &[u8; 16] = b"0123456789abcdef";
+    const BASE64URL_ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    pub enum EncodingError {
+        /// A character outside of the alphabet, uppercase hex digits and padding included.
+        InvalidCharacter(char),
+        /// A length no encoding produces.
+        InvalidLength(usize),
+        /// Base64url whose unused trailing bits are not zero.
+        NonCanonical,
+        /// A number not exactly as wide as its group requires.
+        WrongWidth { expected: usize, actual: usize },
+        /// A number that doesn't fit in the width of its group.
+        TooWide { width: usize, actual: usize },
+    }
+
+    impl Display for EncodingError {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                EncodingError::InvalidCharacter(c) => write!(f, "invalid character {:?}", c),
+                EncodingError::InvalidLength(len) => write!(f, "invalid length {}", len),
+                EncodingError::NonCanonical => write!(f, "non canonical encoding"),
+                EncodingError::WrongWidth { expected, actual } => {
+                    write!(f, "expected {} bytes, got {}", expected, actual)
+                }
+                EncodingError::TooWide { width, actual } => {
+                    write!(f, "{} bytes don't fit in {}", actual, width)
+                }
+            }
+        }
+    }
+
+    impl std::error::Error for EncodingError {}
+
+    /// Big endian bytes without leading zeros, zero being no bytes at all.
+    pub fn minimal(bytes: &[u8]) -> &[u8] {
+        let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+
+        &bytes[start..]
+    }
+
+    pub fn to_hex(bytes: &[u8]) -> String {
+        bytes
+            .iter()
+            .flat_map(|b| [b >> 4, b & 0xf])
+            .map(|nibble| HEX_ALPHABET[nibble as usize] as char)
+            .collect()
+    }
+
+    /// Parses lowercase hex, two digits per byte.
+    pub fn from_hex(s: &str) -> Result<Vec<u8>, EncodingError> {
+        if !s.len().is_multiple_of(2) {
+            return Err(EncodingError::InvalidLength(s.len()));
+        }
+
+        let nibble = |c: u8| {
+            HEX_ALPHABET
+                .iter()
+                .position(|a| *a == c)
+                .map(|nibble| nibble as u8)
+                .ok_or(EncodingError::InvalidCharacter(c as char))
+        };
+
+        s.as_bytes()
+            .chunks(2)
+            .map(|pair| Ok(nibble(pair[0])? << 4 | nibble(pair[1])?))
+            .collect()
+    }
+
+    /// RFC 4648 base64url, without padding.
+    pub fn to_base64url(bytes: &[u8]) -> String {
+        let mut encoded = String::new();
+
+        for chunk in bytes.chunks(3) {
+            let buffer = chunk
+                .iter()
+                .enumerate()
+                .fold(0u32, |buffer, (i, b)| buffer | (*b as u32) << (16 - 8 * i));
+
+            for i in 0..=chunk.len() {
+                encoded.push(BASE64URL_ALPHABET[(buffer >> (18 - 6 * i)) as usize & 63] as char);
+            }
+        }
+
+        encoded
+    }
+
+    /// Parses unpadded base64url, refusing non-zero trailing bits.
+    pub fn from_base64url(s: &str) -> Result<Vec<u8>, EncodingError> {
+        if s.len() % 4 == 1 {
+            return Err(EncodingError::InvalidLength(s.len()));
+        }
+
+        let mut bytes = vec![];
+
+        for chunk in s.as_bytes().chunks(4) {
+            let mut buffer = 0u32;
+            for (i, c) in chunk.iter().enumerate() {
+                let value = BASE64URL_ALPHABET
+                    .iter()
+                    .position(|a| a == c)
+                    .ok_or(EncodingError::InvalidCharacter(*c as char))?;
+                buffer |= (value as u32) << (18 - 6 * i);
+            }
+
+            let len = chunk.len() - 1;
+            if buffer & (0xffffff >> (8 * len)) != 0 {
+                return Err(EncodingError::NonCanonical);
+            }
+
+            bytes.extend_from_slice(&buffer.to_be_bytes()[1..=len]);
+        }
+
+        Ok(bytes)
+    }
+
+    /// The number of bytes every number of a group is encoded with.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub struct Width(pub usize);
+
+    impl Width {
+        /// Enough for any element of Z_p* and any exponent, given the big endian `p`.
+        pub fn of_modulus(p: &[u8]) -> Self {
+            Width(minimal(p).len())
+        }
+
+        /// Left pads the big endian `value` with zeros.
+        pub fn pad(&self, value: &[u8]) -> Result<Vec<u8>, EncodingError> {
+            let value = minimal(value);
+            if value.len() > self.0 {
+                return Err(EncodingError::TooWide {
+                    width: self.0,
+                    actual: value.len(),
+                });
+            }
+
+            let mut padded = vec![0; self.0 - value.len()];
+            padded.extend_from_slice(value);
+
+            Ok(padded)
+        }
+
+        /// Checks that `bytes` are exactly as wide as they must be.
+        pub fn check<'a>(&self, bytes: &'a [u8]) -> Result<&'a [u8], EncodingError> {
+            if bytes.len() != self.0 {
+                return Err(EncodingError::WrongWidth {
+                    expected: self.0,
+                    actual: bytes.len(),
+                });
+            }
+
+            Ok(bytes)
+        }
+
+        pub fn to_hex(&self, value: &[u8]) -> Result<String, EncodingError> {
+            Ok(to_hex(&self.pad(value)?))
+        }
+
+        pub fn from_hex(&self, s: &str) -> Result<Vec<u8>, EncodingError> {
+            let bytes = from_hex(s)?;
+            self.check(&bytes)?;
+
+            Ok(bytes)
+        }
+
+        pub fn to_base64url(&self, value: &[u8]) -> Result<String, EncodingError> {
+            Ok(to_base64url(&self.pad(value)?))
+        }
+
+        pub fn from_base64url(&self, s: &str) -> Result<Vec<u8>, EncodingError> {
+            let bytes = from_base64url(s)?;
+            self.check(&bytes)?;
+
+            Ok(bytes)
+        }
+    }
+
+    /// Serde adapter for byte fields sent as hex: `#[serde(with = "encoding::hex")]`.
+    pub mod hex {
+        use serde::de::Error;
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&super::to_hex(bytes))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<u8>, D::Error> {
+            super::from_hex(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+        }
+
+        /// The same, for lists of byte fields.
+        pub mod seq {
+            use serde::de::Error;
+            use serde::ser::SerializeSeq;
+            use serde::{Deserialize, Deserializer, Serializer};
+
+            pub fn serialize<S: Serializer>(
+                values: &[Vec<u8>],
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(&super::super::to_hex(value))?;
+                }
+
+                seq.end()
+            }
+
+            pub fn deserialize<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Vec<Vec<u8>>, D::Error> {
+                Vec::<String>::deserialize(deserializer)?
+                    .iter()
+                    .map(|s| super::super::from_hex(s).map_err(D::Error::custom))
+                    .collect()
+            }
+        }
+    }
+
+    /// Serde adapter for byte fields sent as base64url: `#[serde(with = "encoding::base64url")]`.
+    pub mod base64url {
+        use serde::de::Error;
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&super::to_base64url(bytes))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<u8>, D::Error> {
+            super::from_base64url(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn hex() {
+            assert_eq!(to_hex(&[0x00, 0x0f, 0xab]), "000fab");
+            assert_eq!(from_hex("000fab").unwrap(), vec![0x00, 0x0f, 0xab]);
+            assert_eq!(from_hex("").unwrap(), Vec::<u8>::new());
+            assert_eq!(from_hex("abc"), Err(EncodingError::InvalidLength(3)));
+            assert_eq!(from_hex("0F"), Err(EncodingError::InvalidCharacter('F')));
+            assert_eq!(from_hex("0x"), Err(EncodingError::InvalidCharacter('x')));
+        }
+
+        #[test]
+        fn base64url() {
+            for (bytes, encoded) in [
+                (&b""[..], ""),
+                (b"f", "Zg"),
+                (b"fo", "Zm8"),
+                (b"foo", "Zm9v"),
+                (b"foob", "Zm9vYg"),
+                (&[0xfb, 0xff][..], "-_8"),
+            ] {
+                assert_eq!(to_base64url(bytes), encoded);
+                assert_eq!(from_base64url(encoded).unwrap(), bytes);
+            }
+
+            assert_eq!(from_base64url("Zh"), Err(EncodingError::NonCanonical));
+            assert_eq!(
+                from_base64url("Zg=="),
+                Err(EncodingError::InvalidCharacter('='))
+            );
+            assert_eq!(
+                from_base64url("Zm9vY"),
+                Err(EncodingError::InvalidLength(5))
+            );
+            assert_eq!(
+                from_base64url("+/"),
+                Err(EncodingError::InvalidCharacter('+'))
+            );
+        }
+
+        #[test]
+        fn width() {
+            let width = Width::of_modulus(&[0x00, 0x01, 0x00, 0x01]);
+
+            assert_eq!(width, Width(3));
+            assert_eq!(width.to_hex(&[0x02]).unwrap(), "000002");
+            assert_eq!(
+                width.to_hex(&[0x00, 0x00, 0x00, 0x00, 0x02]).unwrap(),
+                "000002"
+            );
+            assert_eq!(width.to_hex(&[]).unwrap(), "000000");
+            assert_eq!(
+                width.to_hex(&[0x01, 0x00, 0x00, 0x00]),
+                Err(EncodingError::TooWide {
+                    width: 3,
+                    actual: 4
+                })
+            );
+            assert_eq!(width.from_hex("000002").unwrap(), vec![0, 0, 2]);
+            assert_eq!(
+                width.from_hex("02"),
+                Err(EncodingError::WrongWidth {
+                    expected: 3,
+                    actual: 1
+                })
+            );
+            assert_eq!(width.from_base64url("AAAC").unwrap(), vec![0, 0, 2]);
+        }
+    }
+}
+
+/// Known-answer test vectors of the protocol, for implementations of either side to check
+/// themselves against. Numbers are hex encoded as on the wire, as wide as `p`, but for `x` that
+/// is the password as `password_to_secret` derives it and is not a number of the group.
+pub mod vectors {
+    use crate::core::Choice;
+    use crate::protocol::Version;
+    use serde::Deserialize;
+
+    /// The vectors of `Version::V1`, as published in `vectors/pwd-dl-zkp-1.json`.
+    pub const V1: &str = include_str!("../vectors/pwd-dl-zkp-1.json");
+
+    /// The vectors of `Version::V2`, as published in `vectors/pwd-dl-zkp-2.json`.
+    pub const V2: &str = include_str!("../vectors/pwd-dl-zkp-2.json");
+
+    #[derive(Debug, Deserialize)]
+    pub struct Vectors {
+        pub version: Version,
+        pub groups: Vec<Group>,
+        pub keys: Vec<Key>,
+        pub rounds: Vec<Round>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Group {
+        pub name: String,
+        pub p: String,
+        pub g: String,
+    }
+
+    /// The secret and public key a password yields in a group.
+    #[derive(Debug, Deserialize)]
+    pub struct Key {
+        pub group: String,
+        pub password: String,
+        pub x: String,
+        pub y: String,
+    }
+
+    /// A round of the proof: the commitment `c` of the nonce `r`, the challenge and the response,
+    /// and whether the verifier must accept it. Negative rounds tamper with one of them.
+    #[derive(Debug, Deserialize)]
+    pub struct Round {
+        pub group: String,
+        pub comment: String,
+        pub x: String,
+        pub y: String,
+        pub r: String,
+        pub c: String,
+        pub choice: Choice,
+        pub res: String,
+        pub valid: bool,
+    }
+
+    impl Vectors {
+        pub fn group(&self, name: &str) -> Option<&Group> {
+            self.groups.iter().find(|group| group.name == name)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::encoding::{self, Width};
+
+        #[test]
+        fn canonical() {
+            for (vectors, version) in [(V1, Version::V1), (V2, Version::V2)] {
+                let vectors: Vectors = serde_json::from_str(vectors).unwrap();
+                assert_eq!(vectors.version, version);
+
+                check(&vectors);
+            }
+        }
+
+        fn check(vectors: &Vectors) {
+            for group in &vectors.groups {
+                let width = Width::of_modulus(&encoding::from_hex(&group.p).unwrap());
+                width.from_hex(&group.p).unwrap();
+                width.from_hex(&group.g).unwrap();
+            }
+
+            let canonical = |group: &str, numbers: &[&String]| {
+                let group = vectors.group(group).unwrap();
+                let width = Width::of_modulus(&encoding::from_hex(&group.p).unwrap());
+                for n in numbers {
+                    width.from_hex(n).unwrap();
+                }
+            };
+            for key in &vectors.keys {
+                canonical(&key.group, &[&key.y]);
+            }
+            for round in &vectors.rounds {
+                canonical(&round.group, &[&round.y, &round.r, &round.c, &round.res]);
+            }
+
+            for group in &vectors.groups {
+                let rounds = vectors.rounds.iter().filter(|r| r.group == group.name);
+                assert!(rounds.clone().any(|round| round.valid));
+                assert!(rounds.clone().any(|round| !round.valid));
+            }
+        }
+    }
+}
@@ -1,48 +1,181 @@
-use num_bigint::BigInt;
-use num_traits::Num;
 use pwd_dl_zkp_core::core::Choice;
-use pwd_dl_zkp_peggy::peggy::Peggy;
 use wasm_bindgen::prelude::*;
 
-#[wasm_bindgen]
-pub fn public_key(x: &str, g: &str, p: &str) -> Result<String, JsError> {
-    let x = BigInt::from_str_radix(x, 16)?;
-    let g = BigInt::from_str_radix(g, 16)?;
-    let p = BigInt::from_str_radix(p, 16)?;
+/// The exports with Rust errors and choices, for native callers: `JsError` and `JsValue` can only
+/// be built in a JavaScript host.
+pub mod native {
+    use num_bigint::{BigInt, BigUint, ParseBigIntError};
+    use num_traits::Num;
+    use pwd_dl_zkp_core::core::Choice;
+    use pwd_dl_zkp_core::encoding::{self, EncodingError, Width};
+    use pwd_dl_zkp_peggy::peggy::Peggy;
+    use rand::rngs::OsRng;
+    use std::fmt::{Display, Formatter};
 
-    let y = Peggy::public_key(&x, &g, &p)?;
+    #[derive(Debug)]
+    pub enum Error {
+        Encoding(EncodingError),
+        Parse(ParseBigIntError),
+        /// A `p` no group is built on, that the arithmetic of the prover would choke on.
+        Modulus,
+    }
 
-    Ok(y.to_str_radix(16))
-}
+    impl Display for Error {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Error::Encoding(err) => write!(f, "{}", err),
+                Error::Parse(err) => write!(f, "{}", err),
+                Error::Modulus => write!(f, "p must be odd and greater than 2"),
+            }
+        }
+    }
 
-#[wasm_bindgen]
-pub fn gen_r(p: &str) -> Result<String, JsError> {
-    let p = BigInt::from_str_radix(p, 16)?;
+    impl std::error::Error for Error {}
+
+    impl From<EncodingError> for Error {
+        fn from(err: EncodingError) -> Self {
+            Error::Encoding(err)
+        }
+    }
+
+    impl From<ParseBigIntError> for Error {
+        fn from(err: ParseBigIntError) -> Self {
+            Error::Parse(err)
+        }
+    }
+
+    /// `p` as sent by the server, and the width the numbers of its group are encoded with.
+    fn modulus(p: &str) -> Result<(BigInt, Width), Error> {
+        let p = encoding::from_hex(p)?;
+        let width = Width::of_modulus(&p);
+        let p = Peggy::from_wire(&p, width)?;
+
+        if p <= BigInt::from(2) || !p.bit(0) {
+            return Err(Error::Modulus);
+        }
+
+        Ok((p, width))
+    }
+
+    /// A number of the group, in the canonical hex encoding.
+    fn element(n: &str, width: Width) -> Result<BigInt, Error> {
+        Ok(Peggy::from_wire(&width.from_hex(n)?, width)?)
+    }
+
+    /// `x` is the hex encoded password, not a number of the group, but never a negative one.
+    fn secret(x: &str) -> Result<BigInt, Error> {
+        Ok(BigUint::from_str_radix(x, 16)?.into())
+    }
+
+    fn to_hex(n: &BigInt, width: Width) -> Result<String, Error> {
+        Ok(encoding::to_hex(&Peggy::to_wire(n, width)?))
+    }
+
+    pub fn public_key(x: &str, g: &str, p: &str) -> Result<String, Error> {
+        let (p, width) = modulus(p)?;
+        let x = secret(x)?;
+        let g = element(g, width)?;
+
+        let y = Peggy::public_key(&x, &g, &p)?;
+
+        to_hex(&y, width)
+    }
+
+    pub fn gen_r(g: &str, p: &str) -> Result<String, Error> {
+        let (p, width) = modulus(p)?;
+        let g = element(g, width)?;
+
+        let r = Peggy::gen_r(&Peggy::order(&g, &p), &mut OsRng)?;
+
+        to_hex(&r, width)
+    }
 
-    let r = Peggy::gen_r(&p)?;
+    pub fn calc_c(r: &str, g: &str, p: &str) -> Result<String, Error> {
+        let (p, width) = modulus(p)?;
+        let r = element(r, width)?;
+        let g = element(g, width)?;
 
-    Ok(r.to_str_radix(16))
+        let c = Peggy::calc_c(&r, &g, &p)?;
+
+        to_hex(&c, width)
+    }
+
+    pub fn calc_choice(
+        choice: &Choice,
+        x: &str,
+        r: &str,
+        g: &str,
+        p: &str,
+    ) -> Result<String, Error> {
+        let (p, width) = modulus(p)?;
+        let x = secret(x)?;
+        let r = element(r, width)?;
+        let g = element(g, width)?;
+
+        let res = Peggy::calc_choice(choice, &x, &r, &Peggy::order(&g, &p))?;
+
+        to_hex(&res, width)
+    }
 }
 
+/// `x` is the hex encoded password, not a number of the group.
 #[wasm_bindgen]
-pub fn calc_c(r: &str, g: &str, p: &str) -> Result<String, JsError> {
-    let r = BigInt::from_str_radix(r, 16)?;
-    let g = BigInt::from_str_radix(g, 16)?;
-    let p = BigInt::from_str_radix(p, 16)?;
+pub fn public_key(x: &str, g: &str, p: &str) -> Result<String, JsError> {
+    Ok(native::public_key(x, g, p)?)
+}
 
-    let c = Peggy::calc_c(&r, &g, &p)?;
+/// A nonce drawn from [1, q), q being the order of `g`, as `pwd-dl-zkp/2` wants it.
+#[wasm_bindgen]
+pub fn gen_r(g: &str, p: &str) -> Result<String, JsError> {
+    Ok(native::gen_r(g, p)?)
+}
 
-    Ok(c.to_str_radix(16))
+#[wasm_bindgen]
+pub fn calc_c(r: &str, g: &str, p: &str) -> Result<String, JsError> {
+    Ok(native::calc_c(r, g, p)?)
 }
 
+/// The response to `choice`, reduced mod the order of `g`, as `pwd-dl-zkp/2` wants it.
 #[wasm_bindgen]
-pub fn calc_choice(choice: &JsValue, x: &str, r: &str, p: &str) -> Result<String, JsError> {
+pub fn calc_choice(
+    choice: &JsValue,
+    x: &str,
+    r: &str,
+    g: &str,
+    p: &str,
+) -> Result<String, JsError> {
     let choice: Choice = choice.into_serde()?;
-    let x = BigInt::from_str_radix(x, 16)?;
-    let r = BigInt::from_str_radix(r, 16)?;
-    let p = BigInt::from_str_radix(p, 16)?;
 
-    let res = Peggy::calc_choice(&choice, &x, &r, &p)?;
+    Ok(native::calc_choice(&choice, x, r, g, p)?)
+}
+
+/// Run with `wasm-pack test --node`, the bindings need a JavaScript host.
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use pwd_dl_zkp_core::vectors::{Vectors, V2};
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn vectors() {
+        let vectors: Vectors = serde_json::from_str(V2).unwrap();
+
+        for key in &vectors.keys {
+            let group = vectors.group(&key.group).unwrap();
+
+            assert_eq!(public_key(&key.x, &group.g, &group.p).unwrap(), key.y);
+        }
+
+        for round in vectors.rounds.iter().filter(|round| round.valid) {
+            let group = vectors.group(&round.group).unwrap();
+            let choice = JsValue::from_serde(&round.choice).unwrap();
 
-    Ok(res.to_str_radix(16))
+            assert_eq!(public_key(&round.x, &group.g, &group.p).unwrap(), round.y);
+            assert_eq!(calc_c(&round.r, &group.g, &group.p).unwrap(), round.c);
+            assert_eq!(
+                calc_choice(&choice, &round.x, &round.r, &group.g, &group.p).unwrap(),
+                round.res
+            );
+        }
+    }
 }
@@ -1,21 +1,354 @@
 pub mod peggy {
-    use num_bigint::{BigInt, ParseBigIntError, RandBigInt, ToBigInt};
+    use num_bigint::{BigInt, ParseBigIntError, RandBigInt, Sign, ToBigInt};
+    use num_traits::{One, Zero};
     use pwd_dl_zkp_core::core::Choice;
+    use pwd_dl_zkp_core::encoding::{EncodingError, Width};
+    use pwd_dl_zkp_core::protocol::{Version, CHALLENGE_BITS, CHAUM_PEDERSEN_LABEL, SCHNORR_LABEL};
+    use rand::{CryptoRng, RngCore};
+    use sha2::{Digest, Sha256};
+    use std::fmt::{Display, Formatter};
+    use zeroize::Zeroizing;
+
+    /// A non-interactive Chaum–Pedersen proof that log_g(y1) = log_h(y2).
+    #[derive(Clone, Debug)]
+    pub struct EqualityProof {
+        pub t1: BigInt,
+        pub t2: BigInt,
+        pub s: BigInt,
+    }
+
+    /// A non-interactive Schnorr proof of the knowledge of log_g(y).
+    #[derive(Clone, Debug)]
+    pub struct KnowledgeProof {
+        pub t: BigInt,
+        pub s: BigInt,
+    }
+
+    /// Prover side of an OR proof between the commitments and the response: every branch but
+    /// the known one `k` is already simulated, with its challenge picked in advance.
+    pub struct OrCommitment {
+        pub t: Vec<BigInt>,
+        k: usize,
+        r: Secret,
+        e: Vec<BigInt>,
+        s: Vec<BigInt>,
+    }
+
+    /// The branch challenges, adding up to the verifier's challenge, and responses of an OR proof.
+    #[derive(Clone, Debug)]
+    pub struct OrResponse {
+        pub e: Vec<BigInt>,
+        pub s: Vec<BigInt>,
+    }
+
+    /// Fiat–Shamir challenge: the hash of the label and of the context, length prefixed, then of
+    /// `p` and of every number in the canonical encoding of the group of `p`.
+    fn fiat_shamir(label: &str, context: &[u8], p: &BigInt, numbers: &[&BigInt]) -> BigInt {
+        let width = Peggy::width(p);
+
+        let mut hasher = Sha256::new();
+        hasher.update((label.len() as u32).to_be_bytes());
+        hasher.update(label.as_bytes());
+        hasher.update((context.len() as u32).to_be_bytes());
+        hasher.update(context);
+
+        for number in [p].iter().chain(numbers) {
+            hasher.update(Peggy::to_wire(number, width).unwrap());
+        }
+
+        BigInt::from_bytes_be(Sign::Plus, &hasher.finalize())
+    }
+
+    /// A secret exponent, big-endian, in a buffer wiped once dropped. Its arithmetic never goes
+    /// through `BigInt`, whose digits can't be wiped: only the group elements it raises and the
+    /// responses meant to be sent are numbers.
+    pub struct Secret(Zeroizing<Vec<u8>>);
+
+    impl Secret {
+        pub fn new(bytes: Zeroizing<Vec<u8>>) -> Self {
+            Secret(bytes)
+        }
+
+        /// A buffer of `len` zeros that is never reallocated, hence never leaves copies behind.
+        fn zeros(len: usize) -> Zeroizing<Vec<u8>> {
+            Zeroizing::new(vec![0; len])
+        }
+
+        /// Drawn uniformly from [1, q).
+        fn random<R: RngCore + CryptoRng>(q: &Modulus, rng: &mut R) -> Self {
+            let mask = (1u16 << (q.0[1].ilog2() + 1)).wrapping_sub(1) as u8;
+            let mut r = Self::zeros(q.0.len());
+
+            loop {
+                rng.fill_bytes(&mut r[1..]);
+                r[1] &= mask;
+
+                if r.iter().any(|&byte| byte != 0) && r[..] < q.0[..] {
+                    return Secret(r);
+                }
+            }
+        }
+
+        /// The bits, most significant first.
+        fn bits(&self) -> impl Iterator<Item = bool> + '_ {
+            self.0
+                .iter()
+                .flat_map(|byte| (0..8).rev().map(move |bit| byte >> bit & 1 == 1))
+        }
+
+        /// g^self mod p, by square-and-multiply over the bits.
+        pub fn pow(&self, g: &BigInt, p: &BigInt) -> BigInt {
+            self.bits().fold(BigInt::one(), |acc, bit| {
+                let acc = &acc * &acc % p;
+                if bit {
+                    acc * g % p
+                } else {
+                    acc
+                }
+            })
+        }
+
+        /// self mod q, one bit at a time.
+        fn reduce(&self, q: &Modulus) -> Self {
+            let mut acc = Self::zeros(q.0.len());
+            for bit in self.bits() {
+                shift_in(&mut acc, bit);
+                q.fold(&mut acc);
+            }
+
+            Secret(acc)
+        }
+
+        /// self + other mod q, both being reduced, hence as wide as `q`.
+        fn add(&self, other: &Secret, q: &Modulus) -> Self {
+            let mut sum = Self::zeros(q.0.len());
+            let mut carry = 0;
+            for ((sum, a), b) in sum.iter_mut().zip(self.0.iter()).zip(other.0.iter()).rev() {
+                let digit = *a as u16 + *b as u16 + carry;
+                *sum = digit as u8;
+                carry = digit >> 8;
+            }
+            q.fold(&mut sum);
+
+            Secret(sum)
+        }
+
+        /// e * self mod q, self being reduced and `e` public.
+        fn mul(&self, e: &BigInt, q: &Modulus) -> Self {
+            let mut acc = Secret(Self::zeros(q.0.len()));
+            for bit in (0..e.bits()).rev().map(|bit| e.bit(bit)) {
+                acc = acc.add(&acc, q);
+                if bit {
+                    acc = acc.add(self, q);
+                }
+            }
+
+            acc
+        }
+
+        /// The value, once it gives nothing away: a response to be sent.
+        fn reveal(&self) -> BigInt {
+            BigInt::from_bytes_be(Sign::Plus, &self.0)
+        }
+    }
+
+    /// Takes over big-endian `bytes`, without copying them.
+    impl From<Vec<u8>> for Secret {
+        fn from(bytes: Vec<u8>) -> Self {
+            Secret(Zeroizing::new(bytes))
+        }
+    }
+
+    /// A public modulus, big-endian with a leading zero byte: the sums of two reduced numbers
+    /// fit the width.
+    struct Modulus(Vec<u8>);
+
+    impl Modulus {
+        /// Refuses moduli below 2, which leave no nonce to draw.
+        fn new(q: &BigInt) -> Result<Self, ProverError> {
+            if q < &BigInt::from(2) {
+                return Err(ProverError::Modulus);
+            }
+
+            let mut bytes = vec![0];
+            bytes.extend(q.to_bytes_be().1);
+
+            Ok(Modulus(bytes))
+        }
+
+        /// Subtracts the modulus once from `a` if `a` isn't below it.
+        fn fold(&self, a: &mut [u8]) {
+            if a[..] < self.0[..] {
+                return;
+            }
+
+            let mut borrow = 0;
+            for (a, q) in a.iter_mut().zip(self.0.iter()).rev() {
+                let digit = *a as i16 - *q as i16 - borrow;
+                *a = digit as u8;
+                borrow = (digit < 0) as i16;
+            }
+        }
+    }
+
+    /// a * 2 + bit, in place.
+    fn shift_in(a: &mut [u8], bit: bool) {
+        let mut carry = bit as u8;
+        for byte in a.iter_mut().rev() {
+            let next = *byte >> 7;
+            *byte = *byte << 1 | carry;
+            carry = next;
+        }
+    }
+
+    /// Misuses of a `Prover` that would give its secret away.
+    #[derive(Debug, PartialEq)]
+    pub enum ProverError {
+        /// A response without a commitment, or a second response to the same commitment.
+        NoCommitment,
+        /// A group whose exponents are taken modulo 0 or 1.
+        Modulus,
+    }
+
+    impl Display for ProverError {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                ProverError::NoCommitment => write!(f, "no commitment to respond to"),
+                ProverError::Modulus => write!(f, "the group has no exponents to draw"),
+            }
+        }
+    }
+
+    impl std::error::Error for ProverError {}
+
+    /// The prover of an interactive login, holding `x` and the nonce of the pending round: every
+    /// nonce answers exactly one choice, the two responses to the same `r` being enough to
+    /// recover `x`. Both are wiped once dropped, and so is everything computed from them.
+    pub struct Prover {
+        x: Secret,
+        g: BigInt,
+        p: BigInt,
+        /// The modulus of the exponents, see `Peggy::response_modulus`.
+        q: Modulus,
+        r: Option<Secret>,
+    }
+
+    impl Prover {
+        /// A prover of the knowledge of `x` in the group of `g` and `p` speaking `version`,
+        /// keeping its own copy of `x` reduced mod the response modulus of the version.
+        pub fn new(
+            version: Version,
+            x: &Secret,
+            g: &BigInt,
+            p: &BigInt,
+        ) -> Result<Self, ProverError> {
+            let q = Modulus::new(&Peggy::response_modulus(version, g, p))?;
+
+            Ok(Prover {
+                x: x.reduce(&q),
+                g: g.clone(),
+                p: p.clone(),
+                q,
+                r: None,
+            })
+        }
+
+        pub fn public_key(&self) -> BigInt {
+            self.x.pow(&self.g, &self.p)
+        }
+
+        /// Draws the nonce of a new round and returns its commitment g^r. A pending nonce that
+        /// was never answered is dropped, it gave nothing away.
+        pub fn commit<R: RngCore + CryptoRng>(&mut self, rng: &mut R) -> BigInt {
+            let r = Secret::random(&self.q, rng);
+            let c = r.pow(&self.g, &self.p);
+            self.r = Some(r);
+
+            c
+        }
+
+        /// Answers the verifier's choice for the pending commitment, consuming its nonce.
+        pub fn respond(&mut self, choice: &Choice) -> Result<BigInt, ProverError> {
+            let r = self.r.take().ok_or(ProverError::NoCommitment)?;
+
+            match choice {
+                Choice::R => Ok(r.reveal()),
+                Choice::XRMP => Ok(self.x.add(&r, &self.q).reveal()),
+            }
+        }
+
+        /// Proves the knowledge of `x` without a verifier, the challenge being the hash of
+        /// `context`, of the statement and of the commitment: the proof is only good for whatever
+        /// `context` identifies.
+        pub fn prove_knowledge<R: RngCore + CryptoRng>(
+            &self,
+            context: &[u8],
+            rng: &mut R,
+        ) -> KnowledgeProof {
+            let r = Secret::random(&self.q, rng);
+            let t = r.pow(&self.g, &self.p);
+            let y = self.public_key();
+
+            let e = fiat_shamir(SCHNORR_LABEL, context, &self.p, &[&self.g, &y, &t]);
+            let s = self.x.mul(&e, &self.q).add(&r, &self.q);
+
+            KnowledgeProof { t, s: s.reveal() }
+        }
+    }
 
     pub struct Peggy {}
 
     impl Peggy {
+        /// The width numbers of the group of `p` are encoded with.
+        pub fn width(p: &BigInt) -> Width {
+            Width::of_modulus(&p.to_bytes_be().1)
+        }
+
+        /// `n` in the canonical encoding of its group.
+        pub fn to_wire(n: &BigInt, width: Width) -> Result<Vec<u8>, EncodingError> {
+            width.pad(&n.to_bytes_be().1)
+        }
+
+        /// A number received in the canonical encoding of its group.
+        pub fn from_wire(bytes: &[u8], width: Width) -> Result<BigInt, EncodingError> {
+            Ok(BigInt::from_bytes_be(Sign::Plus, width.check(bytes)?))
+        }
+
         pub fn public_key(x: &BigInt, g: &BigInt, p: &BigInt) -> Result<BigInt, ParseBigIntError> {
             let y = g.modpow(&x, &p);
 
             Ok(y)
         }
 
-        pub fn gen_r(p: &BigInt) -> Result<BigInt, ParseBigIntError> {
-            let mut rng = rand::thread_rng();
-            let low = 0i32.to_bigint().unwrap();
-            let high = p - 2;
-            let r = rng.gen_bigint_range(&low, &high);
+        /// The order of the subgroup `g` generates in Z_p*, `p` being a safe prime: q = (p - 1) / 2
+        /// when `g` is a square, p - 1 when it generates the whole group.
+        pub fn order(g: &BigInt, p: &BigInt) -> BigInt {
+            let q: BigInt = (p - 1) >> 1;
+
+            if g.modpow(&q, p).is_one() {
+                q
+            } else {
+                p - 1
+            }
+        }
+
+        /// The modulus nonces are drawn below and responses reduced by: p - 1 in V1, the order
+        /// of `g` since V2, whose verifiers reject responses that aren't reduced.
+        pub fn response_modulus(version: Version, g: &BigInt, p: &BigInt) -> BigInt {
+            match version {
+                Version::V1 => p - 1,
+                Version::V2 => Self::order(g, p),
+            }
+        }
+
+        /// A nonce drawn uniformly from [1, q), `q` being a multiple of the order of the
+        /// generator it raises.
+        pub fn gen_r<R: RngCore + CryptoRng>(
+            q: &BigInt,
+            rng: &mut R,
+        ) -> Result<BigInt, ParseBigIntError> {
+            let low = 1i32.to_bigint().unwrap();
+            let r = rng.gen_bigint_range(&low, q);
 
             Ok(r)
         }
@@ -26,15 +359,280 @@ pub mod peggy {
             Ok(c)
         }
 
+        /// The response to `choice`, reduced mod `q`, the response modulus of the version spoken.
+        /// `x` and `r` are plain numbers here, for test vectors and tests: `Prover` keeps real ones.
         pub fn calc_choice(
             choice: &Choice,
             x: &BigInt,
             r: &BigInt,
-            p: &BigInt,
+            q: &BigInt,
         ) -> Result<BigInt, ParseBigIntError> {
             match choice {
-                Choice::R => Ok(r.clone()),
-                Choice::XRMP => Ok((x + r) % (p - 1)),
+                Choice::R => Ok(r % q),
+                Choice::XRMP => Ok((x + r) % q),
+            }
+        }
+
+        /// Commitments (g^r, h^r) of a Chaum–Pedersen proof.
+        pub fn calc_equality_c(
+            r: &BigInt,
+            g: &BigInt,
+            h: &BigInt,
+            p: &BigInt,
+        ) -> Result<(BigInt, BigInt), ParseBigIntError> {
+            Ok((g.modpow(r, p), h.modpow(r, p)))
+        }
+
+        /// Response to the verifier's challenge `e`: r + e * x mod (p - 1), with plain numbers as
+        /// `calc_choice`.
+        pub fn calc_equality_res(
+            e: &BigInt,
+            x: &BigInt,
+            r: &BigInt,
+            p: &BigInt,
+        ) -> Result<BigInt, ParseBigIntError> {
+            Ok((r + e * x) % (p - 1))
+        }
+
+        /// Commitments of a CDS OR proof of the knowledge of the discrete log of one of `ys`, the
+        /// one at index `k`: g^r for it, and a simulated g^s * y^-e for all the others. The keys
+        /// may lie outside the subgroup of `g`, exponents are taken mod p - 1.
+        pub fn calc_or_c<R: RngCore + CryptoRng>(
+            k: usize,
+            ys: &[BigInt],
+            g: &BigInt,
+            p: &BigInt,
+            rng: &mut R,
+        ) -> Result<OrCommitment, ProverError> {
+            let order = p - 1;
+
+            let r = Secret::random(&Modulus::new(&order)?, rng);
+            let mut t = vec![];
+            let mut e = vec![];
+            let mut s = vec![];
+
+            for (i, y) in ys.iter().enumerate() {
+                if i == k {
+                    t.push(r.pow(g, p));
+                    e.push(BigInt::zero());
+                    s.push(BigInt::zero());
+                    continue;
+                }
+
+                let e_i = rng.gen_biguint(CHALLENGE_BITS as u64).to_bigint().unwrap();
+                let s_i = rng.gen_bigint_range(&BigInt::one(), &order);
+                let y_minus_e = y.modpow(&(&order - &e_i % &order), p);
+
+                t.push(g.modpow(&s_i, p) * y_minus_e % p);
+                e.push(e_i);
+                s.push(s_i);
+            }
+
+            Ok(OrCommitment { t, k, r, e, s })
+        }
+
+        /// Answers the verifier's challenge `e`: the known branch gets what is left of `e` once
+        /// the simulated challenges are subtracted, and a real response.
+        pub fn calc_or_res(
+            commitment: OrCommitment,
+            e: &BigInt,
+            x: &Secret,
+            p: &BigInt,
+        ) -> Result<OrResponse, ProverError> {
+            let OrCommitment {
+                k,
+                r,
+                e: mut challenges,
+                mut s,
+                ..
+            } = commitment;
+            let modulus = BigInt::one() << CHALLENGE_BITS;
+
+            let simulated: BigInt = challenges.iter().sum();
+            let e_k = ((e - simulated) % &modulus + &modulus) % &modulus;
+
+            let order = Modulus::new(&(p - 1))?;
+            s[k] = x.reduce(&order).mul(&e_k, &order).add(&r, &order).reveal();
+            challenges[k] = e_k;
+
+            Ok(OrResponse { e: challenges, s })
+        }
+
+        /// Proves that the same `x` is behind g^x and h^x without a verifier, the challenge
+        /// being the hash of the statement and of the commitments.
+        pub fn prove_equality<R: RngCore + CryptoRng>(
+            x: &Secret,
+            g: &BigInt,
+            h: &BigInt,
+            p: &BigInt,
+            rng: &mut R,
+        ) -> Result<EqualityProof, ProverError> {
+            // `g` and `h` may generate different subgroups, only p - 1 is a multiple of both orders.
+            let q = Modulus::new(&(p - 1))?;
+            let r = Secret::random(&q, rng);
+            let (t1, t2) = (r.pow(g, p), r.pow(h, p));
+            let (y1, y2) = (x.pow(g, p), x.pow(h, p));
+
+            let e = fiat_shamir(CHAUM_PEDERSEN_LABEL, &[], p, &[g, h, &y1, &y2, &t1, &t2]);
+            let s = x.reduce(&q).mul(&e, &q).add(&r, &q);
+
+            Ok(EqualityProof {
+                t1,
+                t2,
+                s: s.reveal(),
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use num_traits::Num;
+        use pwd_dl_zkp_core::encoding;
+        use pwd_dl_zkp_core::vectors::{Vectors, V1, V2};
+
+        /// Decodes the numbers of a group, checking that they are canonically encoded.
+        fn numbers<const N: usize>(p: &BigInt, hex: [&str; N]) -> [BigInt; N] {
+            let width = Peggy::width(p);
+            hex.map(|n| Peggy::from_wire(&width.from_hex(n).unwrap(), width).unwrap())
+        }
+
+        fn to_hex(n: &BigInt, p: &BigInt) -> String {
+            encoding::to_hex(&Peggy::to_wire(n, Peggy::width(p)).unwrap())
+        }
+
+        #[test]
+        fn prover() {
+            use rand::SeedableRng;
+            use rand_chacha::ChaCha20Rng;
+
+            let (g, p) = (BigInt::from(4), BigInt::from(23));
+            let x = secret(&BigInt::from(6));
+            let mut rng = ChaCha20Rng::seed_from_u64(0);
+
+            for version in [Version::V1, Version::V2] {
+                let mut prover = Prover::new(version, &x, &g, &p).unwrap();
+                let y = prover.public_key();
+                let q = Peggy::response_modulus(version, &g, &p);
+
+                assert_eq!(prover.respond(&Choice::R), Err(ProverError::NoCommitment));
+
+                for choice in [Choice::R, Choice::XRMP, Choice::XRMP, Choice::R] {
+                    let c = prover.commit(&mut rng);
+                    let res = prover.respond(&choice).unwrap();
+
+                    let expected = match choice {
+                        Choice::R => c.clone(),
+                        Choice::XRMP => &c * &y % &p,
+                    };
+                    assert_eq!(g.modpow(&res, &p), expected);
+                    assert!(res < q);
+                    assert_eq!(prover.respond(&choice), Err(ProverError::NoCommitment));
+                }
+
+                let KnowledgeProof { t, s } = prover.prove_knowledge(b"context", &mut rng);
+                let e = fiat_shamir(SCHNORR_LABEL, b"context", &p, &[&g, &y, &t]);
+                assert_eq!(g.modpow(&s, &p), t * y.modpow(&e, &p) % &p);
+            }
+
+            // Groups leaving no nonce to draw.
+            for p in [1, 2] {
+                let prover = Prover::new(Version::V1, &x, &BigInt::from(1), &BigInt::from(p));
+                assert_eq!(prover.err(), Some(ProverError::Modulus));
+            }
+        }
+
+        #[test]
+        fn nonces() {
+            use rand::SeedableRng;
+            use rand_chacha::ChaCha20Rng;
+
+            let p = BigInt::from(23);
+            assert_eq!(Peggy::order(&BigInt::from(5), &p), BigInt::from(22));
+            assert_eq!(Peggy::order(&BigInt::from(4), &p), BigInt::from(11));
+
+            let q = BigInt::from(3);
+            let mut rng = ChaCha20Rng::seed_from_u64(0);
+            let mut drawn: Vec<BigInt> = (0..100)
+                .map(|_| Peggy::gen_r(&q, &mut rng).unwrap())
+                .collect();
+            drawn.sort();
+            drawn.dedup();
+
+            assert_eq!(drawn, [BigInt::from(1), BigInt::from(2)]);
+        }
+
+        fn secret(n: &BigInt) -> Secret {
+            Secret::from(n.to_bytes_be().1)
+        }
+
+        /// The arithmetic of secrets agrees with that of `BigInt`, for moduli of every top byte
+        /// and secrets wider than them.
+        #[test]
+        fn secret_arithmetic() {
+            use rand::SeedableRng;
+            use rand_chacha::ChaCha20Rng;
+
+            let mut rng = ChaCha20Rng::seed_from_u64(0);
+            let p = BigInt::from(1019);
+            let g = BigInt::from(2);
+
+            for bits in [2, 8, 9, 64, 255, 256] {
+                let q =
+                    rng.gen_bigint_range(&(BigInt::one() << (bits - 1)), &(BigInt::one() << bits));
+                let modulus = Modulus::new(&q).unwrap();
+
+                for _ in 0..20 {
+                    let a = rng.gen_biguint(bits * 2).to_bigint().unwrap();
+                    let b = rng.gen_bigint_range(&BigInt::zero(), &q);
+                    let e = rng.gen_biguint(CHALLENGE_BITS as u64).to_bigint().unwrap();
+
+                    let a_q = secret(&a).reduce(&modulus);
+                    let b_q = secret(&b).reduce(&modulus);
+                    assert_eq!(a_q.reveal(), &a % &q);
+                    assert_eq!(a_q.add(&b_q, &modulus).reveal(), (&a + &b) % &q);
+                    assert_eq!(a_q.mul(&e, &modulus).reveal(), &a * &e % &q);
+                    assert_eq!(secret(&a).pow(&g, &p), g.modpow(&a, &p));
+
+                    let r = Secret::random(&modulus, &mut rng).reveal();
+                    assert!(r >= BigInt::one() && r < q);
+                }
+            }
+        }
+
+        #[test]
+        fn vectors() {
+            for vectors in [V1, V2] {
+                check_vectors(&serde_json::from_str(vectors).unwrap());
+            }
+        }
+
+        fn check_vectors(vectors: &Vectors) {
+            for key in &vectors.keys {
+                let group = vectors.group(&key.group).unwrap();
+                let p = BigInt::from_str_radix(&group.p, 16).unwrap();
+                let [g] = numbers(&p, [&group.g]);
+                let x = BigInt::from_str_radix(&key.x, 16).unwrap();
+
+                let y = Peggy::public_key(&x, &g, &p).unwrap();
+                assert_eq!(to_hex(&y, &p), key.y, "{} {}", key.group, key.password);
+            }
+
+            for round in vectors.rounds.iter().filter(|round| round.valid) {
+                let group = vectors.group(&round.group).unwrap();
+                let p = BigInt::from_str_radix(&group.p, 16).unwrap();
+                let [g, r] = numbers(&p, [&group.g, &round.r]);
+                let x = BigInt::from_str_radix(&round.x, 16).unwrap();
+                let q = Peggy::response_modulus(vectors.version, &g, &p);
+
+                let y = Peggy::public_key(&x, &g, &p).unwrap();
+                let c = Peggy::calc_c(&r, &g, &p).unwrap();
+                let res = Peggy::calc_choice(&round.choice, &x, &r, &q).unwrap();
+
+                let name = format!("{} {} {}", vectors.version, round.group, round.comment);
+                assert_eq!(to_hex(&y, &p), round.y, "{}", name);
+                assert_eq!(to_hex(&c, &p), round.c, "{}", name);
+                assert_eq!(to_hex(&res, &p), round.res, "{}", name);
             }
         }
     }
@@ -0,0 +1,239 @@
+//! Exponentiations of a base known in advance, from a table of its powers, with Montgomery
+//! multiplication on 64 bit limbs since the `openssl` crate doesn't expose libcrypto's.
+
+use openssl::bn::{BigNum, BigNumContextRef, BigNumRef};
+use openssl::error::ErrorStack;
+
+/// Bits of the exponent consumed by every row of a table.
+const WINDOW: i32 = 4;
+
+/// Little endian limbs of `n`, which must fit in `limbs` of them.
+fn to_limbs(n: &BigNumRef, limbs: usize) -> Result<Vec<u64>, ErrorStack> {
+    Ok(n.to_vec_padded(limbs as i32 * 8)?
+        .rchunks(8)
+        .map(|chunk| u64::from_be_bytes(chunk.try_into().unwrap()))
+        .collect())
+}
+
+fn from_limbs(limbs: &[u64]) -> Result<BigNum, ErrorStack> {
+    let bytes: Vec<u8> = limbs
+        .iter()
+        .rev()
+        .flat_map(|limb| limb.to_be_bytes())
+        .collect();
+
+    BigNum::from_slice(&bytes)
+}
+
+/// Montgomery arithmetic modulo an odd `m`, with R = 2^(64 * limbs).
+struct Montgomery {
+    m: Vec<u64>,
+    /// -m^-1 mod 2^64.
+    m_inv: u64,
+    /// R^2 mod m, which brings numbers into Montgomery form.
+    r2: Vec<u64>,
+}
+
+impl Montgomery {
+    fn new(m: &BigNumRef, bnctx: &mut BigNumContextRef) -> Result<Self, ErrorStack> {
+        let limbs = (m.num_bytes() as usize).div_ceil(8);
+        let m_limbs = to_limbs(m, limbs)?;
+
+        // Every Newton step doubles the low bits the inverse is right about, from 1 to 64.
+        let mut inv: u64 = 1;
+        for _ in 0..6 {
+            inv = inv.wrapping_mul(2u64.wrapping_sub(m_limbs[0].wrapping_mul(inv)));
+        }
+
+        let mut r2 = BigNum::new()?;
+        r2.lshift(&*BigNum::from_u32(1)?, 128 * limbs as i32)?;
+        let mut reduced = BigNum::new()?;
+        reduced.nnmod(&r2, m, bnctx)?;
+
+        Ok(Montgomery {
+            m: m_limbs,
+            m_inv: inv.wrapping_neg(),
+            r2: to_limbs(&reduced, limbs)?,
+        })
+    }
+
+    /// a * b / R mod m, both being less than m, by coarsely integrated operand scanning.
+    fn mul(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let n = self.m.len();
+        let mut t = vec![0u64; n + 2];
+
+        for &b_i in b {
+            let mut carry = 0u128;
+            for (t_j, &a_j) in t.iter_mut().zip(a) {
+                let sum = *t_j as u128 + a_j as u128 * b_i as u128 + carry;
+                *t_j = sum as u64;
+                carry = sum >> 64;
+            }
+            let sum = t[n] as u128 + carry;
+            t[n] = sum as u64;
+            t[n + 1] = (sum >> 64) as u64;
+
+            // Adding u * m clears the lowest limb, which is shifted out.
+            let u = t[0].wrapping_mul(self.m_inv) as u128;
+            let mut carry = (t[0] as u128 + u * self.m[0] as u128) >> 64;
+            for j in 1..n {
+                let sum = t[j] as u128 + u * self.m[j] as u128 + carry;
+                t[j - 1] = sum as u64;
+                carry = sum >> 64;
+            }
+            let sum = t[n] as u128 + carry;
+            t[n - 1] = sum as u64;
+            t[n] = t[n + 1] + (sum >> 64) as u64;
+        }
+
+        // The result is less than 2m.
+        let reduced = t[n] == 0 && t[..n].iter().rev().lt(self.m.iter().rev());
+        t.truncate(n);
+        if !reduced {
+            let mut borrow = false;
+            for (t_j, m_j) in t.iter_mut().zip(&self.m) {
+                let (diff, b1) = t_j.overflowing_sub(*m_j);
+                let (diff, b2) = diff.overflowing_sub(borrow as u64);
+                *t_j = diff;
+                borrow = b1 || b2;
+            }
+        }
+
+        t
+    }
+}
+
+/// The powers of a base `g` modulo an odd `p`: row i holds g^(d * 2^(WINDOW * i)) for every
+/// digit d but 0, so that g^e costs a multiplication for every non zero digit of `e`, and no
+/// squaring at all.
+pub struct FixedBase {
+    mont: Montgomery,
+    rows: Vec<Vec<Vec<u64>>>,
+}
+
+impl FixedBase {
+    /// The table of `g` modulo `p`, for exponents as wide as `p`. `None` unless `p` is odd and
+    /// greater than 1.
+    pub fn new(
+        g: &BigNumRef,
+        p: &BigNumRef,
+        bnctx: &mut BigNumContextRef,
+    ) -> Result<Option<Self>, ErrorStack> {
+        if p.is_negative() || p.num_bits() < 2 || !p.is_bit_set(0) {
+            return Ok(None);
+        }
+
+        let mont = Montgomery::new(p, bnctx)?;
+        let mut reduced = BigNum::new()?;
+        reduced.nnmod(g, p, bnctx)?;
+        let mut base = mont.mul(&to_limbs(&reduced, mont.m.len())?, &mont.r2);
+
+        let rows = (0..(p.num_bits() as u32).div_ceil(WINDOW as u32))
+            .map(|_| {
+                let mut row = vec![base.clone()];
+                for _ in 2..1 << WINDOW {
+                    row.push(mont.mul(row.last().unwrap(), &base));
+                }
+                base = mont.mul(row.last().unwrap(), &base);
+
+                row
+            })
+            .collect();
+
+        Ok(Some(FixedBase { mont, rows }))
+    }
+
+    /// g^e mod p, `None` when `e` is negative or wider than `p`.
+    pub fn exp(&self, e: &BigNumRef) -> Result<Option<BigNum>, ErrorStack> {
+        if e.is_negative() || e.num_bits() > self.rows.len() as i32 * WINDOW {
+            return Ok(None);
+        }
+
+        let mut acc: Option<Vec<u64>> = None;
+        for (row, bit) in self
+            .rows
+            .iter()
+            .zip((0..e.num_bits()).step_by(WINDOW as usize))
+        {
+            let digit = (0..WINDOW)
+                .filter(|k| e.is_bit_set(bit + k))
+                .fold(0usize, |digit, k| digit | 1 << k);
+            if digit == 0 {
+                continue;
+            }
+
+            let power = &row[digit - 1];
+            acc = Some(match acc {
+                Some(acc) => self.mont.mul(&acc, power),
+                None => power.clone(),
+            });
+        }
+
+        match acc {
+            Some(acc) => {
+                let mut one = vec![0; self.mont.m.len()];
+                one[0] = 1;
+                from_limbs(&self.mont.mul(&acc, &one)).map(Some)
+            }
+            None => BigNum::from_u32(1).map(Some),
+        }
+    }
+
+    /// Bytes taken by the table.
+    pub fn size(&self) -> usize {
+        self.rows.iter().map(Vec::len).sum::<usize>() * self.mont.m.len() * 8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::bn::BigNumContext;
+
+    /// Agrees with BN_mod_exp, from the smallest odd moduli to the widest ones.
+    #[test]
+    fn mod_exp() {
+        let mut bnctx = BigNumContext::new().unwrap();
+        let moduli = [
+            BigNum::from_u32(3).unwrap(),
+            BigNum::from_u32(1019).unwrap(),
+            BigNum::get_rfc2409_prime_768().unwrap(),
+            BigNum::get_rfc3526_prime_2048().unwrap(),
+        ];
+
+        for p in moduli {
+            let mut g = BigNum::new().unwrap();
+            p.rand_range(&mut g).unwrap();
+            let table = FixedBase::new(&g, &p, &mut bnctx).unwrap().unwrap();
+
+            let mut p_1 = p.to_owned().unwrap();
+            p_1.sub_word(1).unwrap();
+            let mut exponents = vec![BigNum::new().unwrap(), BigNum::from_u32(1).unwrap(), p_1];
+            for _ in 0..8 {
+                let mut e = BigNum::new().unwrap();
+                p.rand_range(&mut e).unwrap();
+                exponents.push(e);
+            }
+
+            for e in exponents {
+                let mut expected = BigNum::new().unwrap();
+                expected.mod_exp(&g, &e, &p, &mut bnctx).unwrap();
+                assert_eq!(
+                    table.exp(&e).unwrap().unwrap(),
+                    expected,
+                    "{} {} {}",
+                    g,
+                    e,
+                    p
+                );
+            }
+
+            let mut wider = BigNum::new().unwrap();
+            wider.lshift(&p, WINDOW).unwrap();
+            assert!(table.exp(&wider).unwrap().is_none());
+        }
+
+        let even = BigNum::from_u32(1024).unwrap();
+        assert!(FixedBase::new(&even, &even, &mut bnctx).unwrap().is_none());
+    }
+}
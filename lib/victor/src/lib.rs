@@ -1,20 +1,387 @@
+pub mod fixed_base;
+
 pub mod victor {
-    use openssl::bn::{BigNum, BigNumContext};
+    use crate::fixed_base::FixedBase;
+    use openssl::bn::{BigNum, BigNumContext, BigNumContextRef, BigNumRef};
     use openssl::error::ErrorStack;
+    use openssl::sha::Sha256;
     use pwd_dl_zkp_core::core::Choice;
-    use rand::rngs::ThreadRng;
-    use rand::Rng;
+    use pwd_dl_zkp_core::encoding::Width;
+    use pwd_dl_zkp_core::protocol::{Version, CHALLENGE_BITS, CHAUM_PEDERSEN_LABEL, SCHNORR_LABEL};
+    use rand::{CryptoRng, Rng, RngCore};
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::mem;
+    use std::sync::{Arc, Mutex, OnceLock};
 
     const BITS: i32 = 512;
 
-    fn generate_safe_prime() -> Result<BigNum, ErrorStack> {
+    /// Bits of the random exponents batched checks are combined with, a wrong check goes unnoticed
+    /// with probability 2^-BATCH_BITS.
+    const BATCH_BITS: i32 = 64;
+
+    /// Bytes of fixed-base tables kept, about 120KB for every 512 bit group. Every user has a
+    /// group of their own, so only the most recently used are.
+    const CACHE_BYTES: usize = 32 << 20;
+
+    static GROUPS: Mutex<VecDeque<Arc<Group>>> = Mutex::new(VecDeque::new());
+
+    thread_local! {
+        static BNCTX: RefCell<Option<BigNumContext>> = const { RefCell::new(None) };
+    }
+
+    /// Runs `f` with the context of the thread, created on first use.
+    fn with_bnctx<T>(
+        f: impl FnOnce(&mut BigNumContextRef) -> Result<T, ErrorStack>,
+    ) -> Result<T, ErrorStack> {
+        BNCTX.with(|bnctx| {
+            let mut bnctx = bnctx.borrow_mut();
+            if bnctx.is_none() {
+                *bnctx = Some(BigNumContext::new()?);
+            }
+
+            f(bnctx.as_mut().unwrap())
+        })
+    }
+
+    /// The Legendre symbol (a/p) of `a` modulo the odd prime `p`: 1 for the squares, -1 for the
+    /// other elements of Z_p*, 0 for multiples of `p`. Computed as the Jacobi symbol, by
+    /// quadratic reciprocity, at the cost of a gcd rather than of an exponentiation.
+    fn legendre(
+        a: &BigNumRef,
+        p: &BigNumRef,
+        bnctx: &mut BigNumContextRef,
+    ) -> Result<i32, ErrorStack> {
+        let mut a = {
+            let mut reduced = BigNum::new()?;
+            reduced.nnmod(a, p, bnctx)?;
+            reduced
+        };
+        let mut n = p.to_owned()?;
+        let mut symbol = 1;
+
+        while a.num_bits() > 0 {
+            let twos = (0..).find(|&bit| a.is_bit_set(bit)).unwrap();
+            let mut odd = BigNum::new()?;
+            odd.rshift(&a, twos)?;
+            a = odd;
+
+            // (2/n) is -1 exactly when n = 3 or 5 mod 8.
+            if twos % 2 == 1 && matches!(n.mod_word(8)?, 3 | 5) {
+                symbol = -symbol;
+            }
+
+            mem::swap(&mut a, &mut n);
+            if a.mod_word(4)? == 3 && n.mod_word(4)? == 3 {
+                symbol = -symbol;
+            }
+
+            let mut reduced = BigNum::new()?;
+            reduced.nnmod(&a, &n, bnctx)?;
+            a = reduced;
+        }
+
+        Ok(if n == BigNum::from_u32(1)? { symbol } else { 0 })
+    }
+
+    /// A group Z_p* with the generator `g`, cached with the powers of `g` across rounds and
+    /// sessions.
+    struct Group {
+        p: BigNum,
+        g: BigNum,
+        /// Unless `p` is even.
+        table: Option<FixedBase>,
+        /// The Legendre symbol of `g`, only V2 and batches need it.
+        g_symbol: OnceLock<i32>,
+        /// The order of `g`: q = (p - 1) / 2 when it is a square, p - 1 otherwise.
+        order: OnceLock<BigNum>,
+    }
+
+    impl Group {
+        /// The group of `g` and `p`, its table built on first use.
+        fn get(
+            g: &BigNumRef,
+            p: &BigNumRef,
+            bnctx: &mut BigNumContextRef,
+        ) -> Result<Arc<Self>, ErrorStack> {
+            let mut groups = GROUPS.lock().unwrap();
+            if let Some(i) = groups
+                .iter()
+                .position(|group| *group.p == *p && *group.g == *g)
+            {
+                let group = groups.remove(i).unwrap();
+                groups.push_front(group.clone());
+
+                return Ok(group);
+            }
+            drop(groups);
+
+            let group = Arc::new(Group {
+                p: p.to_owned()?,
+                g: g.to_owned()?,
+                table: FixedBase::new(g, p, bnctx)?,
+                g_symbol: OnceLock::new(),
+                order: OnceLock::new(),
+            });
+
+            let mut groups = GROUPS.lock().unwrap();
+            groups.push_front(group.clone());
+            let mut size = 0;
+            groups.retain(|group| {
+                size += group.table.as_ref().map_or(0, FixedBase::size);
+                size <= CACHE_BYTES
+            });
+
+            Ok(group)
+        }
+
+        fn g_symbol(&self, bnctx: &mut BigNumContextRef) -> Result<i32, ErrorStack> {
+            if let Some(symbol) = self.g_symbol.get() {
+                return Ok(*symbol);
+            }
+
+            let symbol = legendre(&self.g, &self.p, bnctx)?;
+            Ok(*self.g_symbol.get_or_init(|| symbol))
+        }
+
+        fn order(&self, bnctx: &mut BigNumContextRef) -> Result<&BigNumRef, ErrorStack> {
+            if let Some(order) = self.order.get() {
+                return Ok(order);
+            }
+
+            let mut order = BigNum::new()?;
+            if self.g_symbol(bnctx)? == 1 {
+                order.rshift1(&self.p)?;
+            } else {
+                order.checked_sub(&self.p, &*BigNum::from_u32(1)?)?;
+            }
+            Ok(self.order.get_or_init(|| order))
+        }
+
+        /// g^e mod p, from the table when `e` fits in it.
+        fn exp_g(&self, e: &BigNumRef, bnctx: &mut BigNumContextRef) -> Result<BigNum, ErrorStack> {
+            if let Some(table) = &self.table {
+                if let Some(r) = table.exp(e)? {
+                    return Ok(r);
+                }
+            }
+
+            self.exp(&self.g, e, bnctx)
+        }
+
+        /// a^e mod p.
+        fn exp(
+            &self,
+            a: &BigNumRef,
+            e: &BigNumRef,
+            bnctx: &mut BigNumContextRef,
+        ) -> Result<BigNum, ErrorStack> {
+            let mut r = BigNum::new()?;
+            r.mod_exp(a, e, &self.p, bnctx)?;
+
+            Ok(r)
+        }
+
+        /// What g^res must be equal to: c, or c * y.
+        fn round_rhs(
+            &self,
+            round: &Round,
+            bnctx: &mut BigNumContextRef,
+        ) -> Result<BigNum, ErrorStack> {
+            match round.choice {
+                Choice::R => round.c.to_owned(),
+                Choice::XRMP => {
+                    let mut rhs = BigNum::new()?;
+                    rhs.mod_mul(round.c, round.y, &self.p, bnctx)?;
+
+                    Ok(rhs)
+                }
+            }
+        }
+
+        /// Since V2, responses are reduced mod the order of `g`, so that they say nothing of `x`
+        /// but what the exponent does. V1 takes any exponent.
+        fn in_range(
+            &self,
+            version: Version,
+            res: &BigNumRef,
+            bnctx: &mut BigNumContextRef,
+        ) -> Result<bool, ErrorStack> {
+            match version {
+                Version::V1 => Ok(true),
+                Version::V2 => Ok(!res.is_negative() && res < self.order(bnctx)?),
+            }
+        }
+
+        fn check_round(
+            &self,
+            version: Version,
+            round: &Round,
+            bnctx: &mut BigNumContextRef,
+        ) -> Result<bool, ErrorStack> {
+            if !self.in_range(version, round.res, bnctx)? {
+                return Ok(false);
+            }
+
+            Ok(self.exp_g(round.res, bnctx)? == self.round_rhs(round, bnctx)?)
+        }
+
+        /// Checks all the rounds at once, as g^(sum d_i * res_i) = prod rhs_i^d_i for random d_i.
+        /// Z_p* is the product of the subgroup of order q = (p - 1) / 2 and of {1, -1}: random
+        /// exponents only catch wrong checks in the former, the Legendre symbols of both sides
+        /// tell the latter apart exactly.
+        fn check_batch<R: RngCore + CryptoRng>(
+            &self,
+            version: Version,
+            rounds: &[Round],
+            rng: &mut R,
+            bnctx: &mut BigNumContextRef,
+        ) -> Result<bool, ErrorStack> {
+            // Random exponents catch a wrong check with probability 1 - 1/q, worse than
+            // 1 - 2^-BATCH_BITS in groups that small.
+            if self.p.num_bits() <= BATCH_BITS + 1 {
+                return Ok(false);
+            }
+
+            let g_symbol = self.g_symbol(bnctx)?;
+
+            let mut exponent = BigNum::new()?;
+            let mut bases = Vec::with_capacity(rounds.len());
+            let mut ds = Vec::with_capacity(rounds.len());
+            for round in rounds {
+                if !self.in_range(version, round.res, bnctx)? {
+                    return Ok(false);
+                }
+
+                let rhs = self.round_rhs(round, bnctx)?;
+
+                // g^res is reduced, an rhs that isn't never matches it but would once combined.
+                if rhs.is_negative() || rhs >= self.p {
+                    return Ok(false);
+                }
+
+                let lhs_symbol = if round.res.is_bit_set(0) { g_symbol } else { 1 };
+                if legendre(&rhs, &self.p, bnctx)? != lhs_symbol {
+                    return Ok(false);
+                }
+
+                let d = rng.gen::<u64>();
+                let d_bn = BigNum::from_slice(&d.to_be_bytes())?;
+                let mut d_res = BigNum::new()?;
+                d_res.checked_mul(&d_bn, round.res, bnctx)?;
+                let mut sum = BigNum::new()?;
+                sum.checked_add(&exponent, &d_res)?;
+                exponent.nnmod(&sum, self.order(bnctx)?, bnctx)?;
+
+                bases.push(rhs);
+                ds.push(d);
+            }
+
+            // Every base raised to its own d at once, squaring once per bit of the d.
+            let mut acc = BigNum::from_u32(1)?;
+            for bit in (0..BATCH_BITS).rev() {
+                let mut next = BigNum::new()?;
+                next.mod_sqr(&acc, &self.p, bnctx)?;
+                acc = next;
+                for (base, d) in bases.iter().zip(&ds) {
+                    if d >> bit & 1 == 1 {
+                        let mut next = BigNum::new()?;
+                        next.mod_mul(&acc, base, &self.p, bnctx)?;
+                        acc = next;
+                    }
+                }
+            }
+
+            Ok(self.exp_g(&exponent, bnctx)? == acc)
+        }
+
+        /// Checks that `lhs` = t * y^e mod p.
+        fn check(
+            &self,
+            lhs: &BigNumRef,
+            t: &BigNumRef,
+            y: &BigNumRef,
+            e: &BigNumRef,
+            bnctx: &mut BigNumContextRef,
+        ) -> Result<bool, ErrorStack> {
+            let y_e = self.exp(y, e, bnctx)?;
+            let mut rhs = BigNum::new()?;
+            rhs.mod_mul(t, &y_e, &self.p, bnctx)?;
+
+            Ok(lhs == &*rhs)
+        }
+    }
+
+    /// The statement of a Chaum–Pedersen proof: log_g(y1) = log_h(y2) in Z_p*.
+    pub struct Equality<'a> {
+        pub g: &'a BigNumRef,
+        pub h: &'a BigNumRef,
+        pub y1: &'a BigNumRef,
+        pub y2: &'a BigNumRef,
+        pub p: &'a BigNumRef,
+    }
+
+    /// A non-interactive Chaum–Pedersen proof, as produced by `Peggy::prove_equality`.
+    pub struct EqualityProof {
+        pub t1: BigNum,
+        pub t2: BigNum,
+        pub s: BigNum,
+    }
+
+    /// A non-interactive Schnorr proof, as produced by `Prover::prove_knowledge`.
+    pub struct KnowledgeProof {
+        pub t: BigNum,
+        pub s: BigNum,
+    }
+
+    /// A round of the interactive proof, as checked by `Victor::verify`.
+    pub struct Round<'a> {
+        pub choice: &'a Choice,
+        pub res: &'a BigNumRef,
+        pub c: &'a BigNumRef,
+        pub y: &'a BigNumRef,
+    }
+
+    /// A CDS OR proof, as produced by `Peggy::calc_or_c` and `Peggy::calc_or_res`: one
+    /// commitment, challenge and response for every candidate public key.
+    pub struct OrProof {
+        pub t: Vec<BigNum>,
+        pub e: Vec<BigNum>,
+        pub s: Vec<BigNum>,
+    }
+
+    /// Fiat–Shamir challenge, it must hash exactly what the proofs of `Peggy` hash.
+    fn fiat_shamir(
+        label: &str,
+        context: &[u8],
+        p: &BigNumRef,
+        numbers: &[&BigNumRef],
+    ) -> Result<BigNum, ErrorStack> {
+        let width = Width::of_modulus(&p.to_vec()).0 as i32;
+
+        let mut hasher = Sha256::new();
+        hasher.update(&(label.len() as u32).to_be_bytes());
+        hasher.update(label.as_bytes());
+        hasher.update(&(context.len() as u32).to_be_bytes());
+        hasher.update(context);
+
+        for number in [p].iter().chain(numbers) {
+            hasher.update(&number.to_vec_padded(width)?);
+        }
+
+        BigNum::from_slice(&hasher.finish())
+    }
+
+    /// A random prime p such that (p - 1) / 2 is prime too.
+    pub fn generate_safe_prime(bits: i32) -> Result<BigNum, ErrorStack> {
         let mut prime = BigNum::new()?;
-        prime.generate_prime(BITS, true, None, None)?;
+        prime.generate_prime(bits, true, None, None)?;
 
         Ok(prime)
     }
 
-    fn find_cyclic_group_generator(p: &BigNum) -> Result<BigNum, ErrorStack> {
+    /// A random generator of Z_p*, `p` being a safe prime.
+    pub fn find_cyclic_group_generator(p: &BigNum) -> Result<BigNum, ErrorStack> {
         let mut q = BigNum::from_dec_str(p.to_dec_str()?.to_string().as_str())?;
         q.sub_word(1)?;
         q.div_word(2)?;
@@ -60,13 +427,35 @@ pub mod victor {
         }
 
         pub fn handshake(&self) -> Result<(BigNum, BigNum), ErrorStack> {
-            let p = generate_safe_prime()?;
+            self.generate_group(BITS)
+        }
+
+        pub fn generate_group(&self, bits: i32) -> Result<(BigNum, BigNum), ErrorStack> {
+            let p = generate_safe_prime(bits)?;
             let g = find_cyclic_group_generator(&p)?;
 
             return Ok((p, g));
         }
 
-        pub fn pick_choice(&self, rng: &mut ThreadRng) -> Choice {
+        /// Checks that `p` is a safe prime and that `g` is neither 1 nor -1, hence that it
+        /// generates either the whole group or its subgroup of prime order (p - 1) / 2.
+        pub fn validate_group(&self, p: &BigNum, g: &BigNum) -> Result<bool, ErrorStack> {
+            let one = BigNum::from_u32(1)?;
+            let mut bnctx = BigNumContext::new()?;
+
+            let mut q = BigNum::new()?;
+            q.rshift1(p)?;
+
+            let mut p_minus_one = BigNum::new()?;
+            p_minus_one.checked_sub(p, &one)?;
+
+            Ok(p.is_prime(64, &mut bnctx)?
+                && q.is_prime(64, &mut bnctx)?
+                && g > &one
+                && g < &p_minus_one)
+        }
+
+        pub fn pick_choice<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Choice {
             if rng.gen::<bool>() {
                 Choice::XRMP
             } else {
@@ -74,8 +463,11 @@ pub mod victor {
             }
         }
 
+        /// Checks a round of `version` in the group of `g` and `p`.
+        #[allow(clippy::too_many_arguments)]
         pub fn verify(
             &self,
+            version: Version,
             choice: &Choice,
             res: &BigNum,
             c: &BigNum,
@@ -83,19 +475,238 @@ pub mod victor {
             g: &BigNum,
             p: &BigNum,
         ) -> Result<bool, ErrorStack> {
-            let mut bnctx = BigNumContext::new()?;
+            let round = Round { choice, res, c, y };
 
-            let mut lhs = BigNum::new()?;
-            let mut rhs = BigNum::new()?;
-            rhs.mod_exp(&g, &res, &p, &mut bnctx)?;
+            with_bnctx(|bnctx| Group::get(g, p, bnctx)?.check_round(version, &round, bnctx))
+        }
 
-            match choice {
-                Choice::R => Ok(c == &rhs),
-                Choice::XRMP => {
-                    lhs.mod_mul(&c, &y, &p, &mut bnctx)?;
+        /// Checks many rounds of `version` in the group of `g` and `p`, of one or of many keys, at
+        /// the cost of little more than a single one. Only when some round is wrong are they
+        /// checked one by one, to tell which.
+        pub fn verify_batch<R: RngCore + CryptoRng>(
+            &self,
+            version: Version,
+            rounds: &[Round],
+            g: &BigNumRef,
+            p: &BigNumRef,
+            rng: &mut R,
+        ) -> Result<Vec<bool>, ErrorStack> {
+            with_bnctx(|bnctx| {
+                let group = Group::get(g, p, bnctx)?;
+
+                if group.check_batch(version, rounds, rng, bnctx)? {
+                    return Ok(vec![true; rounds.len()]);
+                }
+
+                rounds
+                    .iter()
+                    .map(|round| group.check_round(version, round, bnctx))
+                    .collect()
+            })
+        }
+
+        /// A challenge for interactive Chaum–Pedersen and OR proofs.
+        pub fn pick_challenge<R: RngCore + CryptoRng>(
+            &self,
+            rng: &mut R,
+        ) -> Result<BigNum, ErrorStack> {
+            BigNum::from_slice(&rng.gen::<[u8; CHALLENGE_BITS / 8]>())
+        }
+
+        /// Checks g^s = t1 * y1^e and h^s = t2 * y2^e.
+        pub fn verify_equality(
+            &self,
+            statement: &Equality,
+            t1: &BigNumRef,
+            t2: &BigNumRef,
+            e: &BigNumRef,
+            s: &BigNumRef,
+        ) -> Result<bool, ErrorStack> {
+            with_bnctx(|bnctx| {
+                let group = Group::get(statement.g, statement.p, bnctx)?;
 
-                    Ok(lhs == rhs)
+                let g_s = group.exp_g(s, bnctx)?;
+                if !group.check(&g_s, t1, statement.y1, e, bnctx)? {
+                    return Ok(false);
                 }
+
+                let h_s = group.exp(statement.h, s, bnctx)?;
+                group.check(&h_s, t2, statement.y2, e, bnctx)
+            })
+        }
+
+        /// Checks that the branch challenges add up to `challenge` modulo 2^CHALLENGE_BITS and that
+        /// g^s_i = t_i * y_i^e_i for every branch, learning nothing about which key the prover owns.
+        pub fn verify_or(
+            &self,
+            ys: &[BigNum],
+            g: &BigNumRef,
+            p: &BigNumRef,
+            challenge: &BigNumRef,
+            proof: &OrProof,
+        ) -> Result<bool, ErrorStack> {
+            let n = ys.len();
+            if n == 0 || proof.t.len() != n || proof.e.len() != n || proof.s.len() != n {
+                return Ok(false);
+            }
+
+            let mut sum = BigNum::new()?;
+            for e_i in proof.e.iter() {
+                let mut next = BigNum::new()?;
+                next.checked_add(&sum, e_i)?;
+                sum = next;
+            }
+            // BN_mask_bits fails on numbers that are already narrower than the mask.
+            if sum.num_bits() > CHALLENGE_BITS as i32 {
+                sum.mask_bits(CHALLENGE_BITS as i32)?;
+            }
+            if &*sum != challenge {
+                return Ok(false);
+            }
+
+            with_bnctx(|bnctx| {
+                let group = Group::get(g, p, bnctx)?;
+
+                for (((y, t), e), s) in ys.iter().zip(&proof.t).zip(&proof.e).zip(&proof.s) {
+                    let g_s = group.exp_g(s, bnctx)?;
+                    if !group.check(&g_s, t, y, e, bnctx)? {
+                        return Ok(false);
+                    }
+                }
+
+                Ok(true)
+            })
+        }
+
+        /// Verifies a Fiat–Shamir proof, recomputing the challenge from the statement.
+        pub fn verify_equality_proof(
+            &self,
+            statement: &Equality,
+            proof: &EqualityProof,
+        ) -> Result<bool, ErrorStack> {
+            let e = fiat_shamir(
+                CHAUM_PEDERSEN_LABEL,
+                &[],
+                statement.p,
+                &[
+                    statement.g,
+                    statement.h,
+                    statement.y1,
+                    statement.y2,
+                    &proof.t1,
+                    &proof.t2,
+                ],
+            )?;
+
+            self.verify_equality(statement, &proof.t1, &proof.t2, &e, &proof.s)
+        }
+
+        /// Verifies a Fiat–Shamir proof of the knowledge of log_g(y), made for `context`.
+        pub fn verify_knowledge(
+            &self,
+            y: &BigNumRef,
+            g: &BigNumRef,
+            p: &BigNumRef,
+            context: &[u8],
+            proof: &KnowledgeProof,
+        ) -> Result<bool, ErrorStack> {
+            let e = fiat_shamir(SCHNORR_LABEL, context, p, &[g, y, &proof.t])?;
+
+            with_bnctx(|bnctx| {
+                let group = Group::get(g, p, bnctx)?;
+                let g_s = group.exp_g(&proof.s, bnctx)?;
+
+                group.check(&g_s, &proof.t, y, &e, bnctx)
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use pwd_dl_zkp_core::vectors::{Vectors, V1, V2};
+        use rand::thread_rng;
+
+        /// The Legendre symbols of every element of small groups, by Euler's criterion.
+        #[test]
+        fn legendre_symbols() {
+            let mut bnctx = BigNumContext::new().unwrap();
+
+            for p in [3u32, 5, 7, 11, 23, 47, 1019] {
+                let p = BigNum::from_u32(p).unwrap();
+                let mut half = BigNum::new().unwrap();
+                half.rshift1(&p).unwrap();
+                let mut p_1 = p.to_owned().unwrap();
+                p_1.sub_word(1).unwrap();
+
+                for a in 0..3 * p.to_dec_str().unwrap().parse::<u32>().unwrap() {
+                    let a = BigNum::from_u32(a).unwrap();
+                    let mut euler = BigNum::new().unwrap();
+                    euler.mod_exp(&a, &half, &p, &mut bnctx).unwrap();
+                    let expected = match euler {
+                        _ if euler.num_bits() == 0 => 0,
+                        _ if euler == p_1 => -1,
+                        _ => 1,
+                    };
+
+                    assert_eq!(
+                        legendre(&a, &p, &mut bnctx).unwrap(),
+                        expected,
+                        "{} {}",
+                        a,
+                        p
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn vectors() {
+            for vectors in [V1, V2] {
+                check_vectors(&serde_json::from_str(vectors).unwrap());
+            }
+        }
+
+        fn check_vectors(vectors: &Vectors) {
+            let version = vectors.version;
+            let victor = Victor::new();
+            let hex = |n: &str| BigNum::from_hex_str(n).unwrap();
+
+            for group in &vectors.groups {
+                let (p, g) = (hex(&group.p), hex(&group.g));
+                let rounds: Vec<_> = vectors
+                    .rounds
+                    .iter()
+                    .filter(|round| round.group == group.name)
+                    .collect();
+                let numbers: Vec<_> = rounds
+                    .iter()
+                    .map(|round| (hex(&round.res), hex(&round.c), hex(&round.y)))
+                    .collect();
+
+                for (round, (res, c, y)) in rounds.iter().zip(&numbers) {
+                    let verdict = victor
+                        .verify(version, &round.choice, res, c, y, &g, &p)
+                        .unwrap();
+                    let name = format!("{} {} {}", version, group.name, round.comment);
+                    assert_eq!(verdict, round.valid, "{}", name);
+                }
+
+                let batch: Vec<_> = rounds
+                    .iter()
+                    .zip(&numbers)
+                    .map(|(round, (res, c, y))| Round {
+                        choice: &round.choice,
+                        res,
+                        c,
+                        y,
+                    })
+                    .collect();
+                let verdicts = victor
+                    .verify_batch(version, &batch, &g, &p, &mut thread_rng())
+                    .unwrap();
+                let expected: Vec<_> = rounds.iter().map(|round| round.valid).collect();
+                assert_eq!(verdicts, expected, "{} {}", version, group.name);
             }
         }
     }
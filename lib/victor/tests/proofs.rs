@@ -0,0 +1,333 @@
+//! The proofs of `Peggy`, checked by `Victor`.
+
+use num_bigint::BigInt;
+use num_traits::Num;
+use openssl::bn::BigNum;
+use pwd_dl_zkp_core::core::Choice;
+use pwd_dl_zkp_core::protocol::Version;
+use pwd_dl_zkp_core::vectors::{Vectors, V2};
+use pwd_dl_zkp_peggy::peggy::{Peggy, Prover, Secret};
+use pwd_dl_zkp_victor::victor::{Equality, EqualityProof, KnowledgeProof, OrProof, Round, Victor};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+fn bigint_to_bignum(i: &BigInt) -> BigNum {
+    BigNum::from_hex_str(i.to_str_radix(16).as_str()).unwrap()
+}
+
+fn bignum_to_bigint(i: &BigNum) -> BigInt {
+    BigInt::from_str_radix(i.to_hex_str().unwrap().to_string().as_str(), 16).unwrap()
+}
+
+fn secret(x: &BigInt) -> Secret {
+    Secret::from(x.to_bytes_be().1)
+}
+
+#[test]
+fn chaum_pedersen() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let victor = Victor::new();
+
+    let (p_bignum, g_bignum) = victor.handshake().unwrap();
+    let p_bigint = bignum_to_bigint(&p_bignum);
+    let g_bigint = bignum_to_bigint(&g_bignum);
+    let q_bigint = Peggy::order(&g_bigint, &p_bigint);
+
+    let k_bigint = Peggy::gen_r(&q_bigint, &mut rng).unwrap();
+    let h_bigint = Peggy::public_key(&k_bigint, &g_bigint, &p_bigint).unwrap();
+    let h_bignum = bigint_to_bignum(&h_bigint);
+
+    let x_bigint = Peggy::gen_r(&q_bigint, &mut rng).unwrap();
+    let y1_bignum = bigint_to_bignum(&Peggy::public_key(&x_bigint, &g_bigint, &p_bigint).unwrap());
+    let y2_bignum = bigint_to_bignum(&Peggy::public_key(&x_bigint, &h_bigint, &p_bigint).unwrap());
+    let other_bignum =
+        bigint_to_bignum(&Peggy::public_key(&(&x_bigint + 1), &h_bigint, &p_bigint).unwrap());
+
+    let statement = Equality {
+        g: &g_bignum,
+        h: &h_bignum,
+        y1: &y1_bignum,
+        y2: &y2_bignum,
+        p: &p_bignum,
+    };
+
+    let r_bigint = Peggy::gen_r(&q_bigint, &mut rng).unwrap();
+    let (t1_bigint, t2_bigint) =
+        Peggy::calc_equality_c(&r_bigint, &g_bigint, &h_bigint, &p_bigint).unwrap();
+    let e_bignum = victor.pick_challenge(&mut rng).unwrap();
+    let s_bigint = Peggy::calc_equality_res(
+        &bignum_to_bigint(&e_bignum),
+        &x_bigint,
+        &r_bigint,
+        &p_bigint,
+    )
+    .unwrap();
+    let (t1_bignum, t2_bignum, s_bignum) = (
+        bigint_to_bignum(&t1_bigint),
+        bigint_to_bignum(&t2_bigint),
+        bigint_to_bignum(&s_bigint),
+    );
+
+    assert!(victor
+        .verify_equality(&statement, &t1_bignum, &t2_bignum, &e_bignum, &s_bignum)
+        .unwrap());
+    assert!(!victor
+        .verify_equality(
+            &Equality {
+                y2: &other_bignum,
+                ..statement
+            },
+            &t1_bignum,
+            &t2_bignum,
+            &e_bignum,
+            &s_bignum
+        )
+        .unwrap());
+
+    let proof = Peggy::prove_equality(
+        &secret(&x_bigint),
+        &g_bigint,
+        &h_bigint,
+        &p_bigint,
+        &mut rng,
+    )
+    .unwrap();
+    let proof = EqualityProof {
+        t1: bigint_to_bignum(&proof.t1),
+        t2: bigint_to_bignum(&proof.t2),
+        s: bigint_to_bignum(&proof.s),
+    };
+
+    assert!(victor.verify_equality_proof(&statement, &proof).unwrap());
+    assert!(!victor
+        .verify_equality_proof(
+            &Equality {
+                y2: &other_bignum,
+                ..statement
+            },
+            &proof
+        )
+        .unwrap());
+}
+
+#[test]
+fn knowledge() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let victor = Victor::new();
+
+    let (p_bignum, g_bignum) = victor.handshake().unwrap();
+    let p_bigint = bignum_to_bigint(&p_bignum);
+    let g_bigint = bignum_to_bigint(&g_bignum);
+    let q_bigint = Peggy::order(&g_bigint, &p_bigint);
+
+    let x_bigint = Peggy::gen_r(&q_bigint, &mut rng).unwrap();
+    let y_bignum = bigint_to_bignum(&Peggy::public_key(&x_bigint, &g_bigint, &p_bigint).unwrap());
+    let other_bignum =
+        bigint_to_bignum(&Peggy::public_key(&(&x_bigint + 1), &g_bigint, &p_bigint).unwrap());
+
+    let proof = Prover::new(Version::V2, &secret(&x_bigint), &g_bigint, &p_bigint)
+        .unwrap()
+        .prove_knowledge(b"session", &mut rng);
+    let proof = KnowledgeProof {
+        t: bigint_to_bignum(&proof.t),
+        s: bigint_to_bignum(&proof.s),
+    };
+
+    assert!(victor
+        .verify_knowledge(&y_bignum, &g_bignum, &p_bignum, b"session", &proof)
+        .unwrap());
+    assert!(!victor
+        .verify_knowledge(&y_bignum, &g_bignum, &p_bignum, b"other session", &proof)
+        .unwrap());
+    assert!(!victor
+        .verify_knowledge(&other_bignum, &g_bignum, &p_bignum, b"session", &proof)
+        .unwrap());
+}
+
+#[test]
+fn or_composition() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let victor = Victor::new();
+
+    let (p_bignum, g_bignum) = victor.handshake().unwrap();
+    let p_bigint = bignum_to_bigint(&p_bignum);
+    let g_bigint = bignum_to_bigint(&g_bignum);
+    let q_bigint = Peggy::order(&g_bigint, &p_bigint);
+
+    // A single key has a single challenge, the server's.
+    for n in [1, 4] {
+        let xs_bigint: Vec<BigInt> = (0..n)
+            .map(|_| Peggy::gen_r(&q_bigint, &mut rng).unwrap())
+            .collect();
+        let ys_bigint: Vec<BigInt> = xs_bigint
+            .iter()
+            .map(|x| Peggy::public_key(x, &g_bigint, &p_bigint).unwrap())
+            .collect();
+        let ys_bignum: Vec<BigNum> = ys_bigint.iter().map(bigint_to_bignum).collect();
+
+        for (k, x_bigint) in xs_bigint.iter().enumerate() {
+            let commitment =
+                Peggy::calc_or_c(k, &ys_bigint, &g_bigint, &p_bigint, &mut rng).unwrap();
+            let t_bignum: Vec<BigNum> = commitment.t.iter().map(bigint_to_bignum).collect();
+
+            let challenge_bignum = victor.pick_challenge(&mut rng).unwrap();
+            let response = Peggy::calc_or_res(
+                commitment,
+                &bignum_to_bigint(&challenge_bignum),
+                &secret(x_bigint),
+                &p_bigint,
+            )
+            .unwrap();
+
+            let proof = OrProof {
+                t: t_bignum,
+                e: response.e.iter().map(bigint_to_bignum).collect(),
+                s: response.s.iter().map(bigint_to_bignum).collect(),
+            };
+
+            assert!(victor
+                .verify_or(&ys_bignum, &g_bignum, &p_bignum, &challenge_bignum, &proof)
+                .unwrap());
+
+            let other_challenge_bignum = victor.pick_challenge(&mut rng).unwrap();
+            assert!(!victor
+                .verify_or(
+                    &ys_bignum,
+                    &g_bignum,
+                    &p_bignum,
+                    &other_challenge_bignum,
+                    &proof
+                )
+                .unwrap());
+        }
+    }
+}
+
+#[test]
+fn batch() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let victor = Victor::new();
+
+    let (p_bignum, g_bignum) = victor.handshake().unwrap();
+    let p_bigint = bignum_to_bigint(&p_bignum);
+    let g_bigint = bignum_to_bigint(&g_bignum);
+    let q_bigint = Peggy::order(&g_bigint, &p_bigint);
+
+    // Rounds of three keys, in the same group.
+    let xs_bigint: Vec<BigInt> = (0..3)
+        .map(|_| Peggy::gen_r(&q_bigint, &mut rng).unwrap())
+        .collect();
+    let ys_bignum: Vec<BigNum> = xs_bigint
+        .iter()
+        .map(|x| bigint_to_bignum(&Peggy::public_key(x, &g_bigint, &p_bigint).unwrap()))
+        .collect();
+
+    let mut rounds: Vec<(Choice, BigNum, BigNum, usize)> = (0..30)
+        .map(|i| {
+            let k = i % xs_bigint.len();
+            let r_bigint = Peggy::gen_r(&q_bigint, &mut rng).unwrap();
+            let c_bigint = Peggy::calc_c(&r_bigint, &g_bigint, &p_bigint).unwrap();
+            let choice = victor.pick_choice(&mut rng);
+            let res_bigint =
+                Peggy::calc_choice(&choice, &xs_bigint[k], &r_bigint, &q_bigint).unwrap();
+
+            (
+                choice,
+                bigint_to_bignum(&res_bigint),
+                bigint_to_bignum(&c_bigint),
+                k,
+            )
+        })
+        .collect();
+
+    let mut verify_batch = |rounds: &[(Choice, BigNum, BigNum, usize)]| {
+        let rounds: Vec<Round> = rounds
+            .iter()
+            .map(|(choice, res, c, k)| Round {
+                choice,
+                res,
+                c,
+                y: &ys_bignum[*k],
+            })
+            .collect();
+
+        victor
+            .verify_batch(Version::V2, &rounds, &g_bignum, &p_bignum, &mut rng)
+            .unwrap()
+    };
+
+    assert!(verify_batch(&rounds).iter().all(|valid| *valid));
+
+    // An unreduced commitment of an R round, the products of a batch would reduce it.
+    let i = (0..rounds.len())
+        .find(|i| matches!(rounds[*i].0, Choice::R))
+        .unwrap();
+    let c = rounds[i].2.to_owned().unwrap();
+    rounds[i].2 = &c + &p_bignum;
+    let valid = verify_batch(&rounds);
+    for (j, valid) in valid.iter().enumerate() {
+        assert_eq!(*valid, j != i);
+    }
+    rounds[i].2 = c;
+
+    // A wrong response, and two commitments multiplied by -1, whose Legendre symbols would
+    // cancel out in a product.
+    rounds[3].1.add_word(1).unwrap();
+    for i in [10, 20] {
+        let c = &rounds[i].2;
+        rounds[i].2 = &p_bignum - c;
+    }
+
+    let valid = verify_batch(&rounds);
+    for (i, valid) in valid.iter().enumerate() {
+        assert_eq!(*valid, ![3, 10, 20].contains(&i));
+    }
+}
+
+#[test]
+fn deterministic() {
+    let vectors: Vectors = serde_json::from_str(V2).unwrap();
+    let group = vectors.group("generated-512").unwrap();
+    let p_bigint = BigInt::from_str_radix(&group.p, 16).unwrap();
+    let g_bigint = BigInt::from_str_radix(&group.g, 16).unwrap();
+    let q_bigint = Peggy::order(&g_bigint, &p_bigint);
+    let (p_bignum, g_bignum) = (bigint_to_bignum(&p_bigint), bigint_to_bignum(&g_bigint));
+    let victor = Victor::new();
+
+    // The commitments, choices and responses of a whole proof, with seeded RNGs on both sides.
+    let transcript = |seed: u64| {
+        let mut prover_rng = ChaCha20Rng::seed_from_u64(seed);
+        let mut verifier_rng = ChaCha20Rng::seed_from_u64(!seed);
+
+        let x_bigint = Peggy::gen_r(&q_bigint, &mut prover_rng).unwrap();
+        let y_bignum =
+            bigint_to_bignum(&Peggy::public_key(&x_bigint, &g_bigint, &p_bigint).unwrap());
+
+        (0..10)
+            .map(|_| {
+                let r_bigint = Peggy::gen_r(&q_bigint, &mut prover_rng).unwrap();
+                let c_bigint = Peggy::calc_c(&r_bigint, &g_bigint, &p_bigint).unwrap();
+                let choice = victor.pick_choice(&mut verifier_rng);
+                let res_bigint =
+                    Peggy::calc_choice(&choice, &x_bigint, &r_bigint, &q_bigint).unwrap();
+
+                assert!(victor
+                    .verify(
+                        Version::V2,
+                        &choice,
+                        &bigint_to_bignum(&res_bigint),
+                        &bigint_to_bignum(&c_bigint),
+                        &y_bignum,
+                        &g_bignum,
+                        &p_bignum,
+                    )
+                    .unwrap());
+
+                (c_bigint, matches!(choice, Choice::XRMP), res_bigint)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(transcript(1), transcript(1));
+    assert_ne!(transcript(1), transcript(2));
+}
@@ -0,0 +1,191 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use num_bigint::{BigInt, Sign};
+use openssl::bn::{BigNum, BigNumContext};
+use pwd_dl_zkp_core::core::Choice;
+use pwd_dl_zkp_core::protocol::Version;
+use pwd_dl_zkp_peggy::peggy::Peggy;
+use pwd_dl_zkp_victor::fixed_base::FixedBase;
+use pwd_dl_zkp_victor::victor::{find_cyclic_group_generator, generate_safe_prime, Round, Victor};
+use rand::thread_rng;
+
+/// Sizes of the groups generated on the fly, larger safe primes take minutes.
+const GENERATED_BITS: [i32; 3] = [256, 512, 1024];
+
+fn to_bigint(n: &BigNum) -> BigInt {
+    BigInt::from_bytes_be(Sign::Plus, &n.to_vec())
+}
+
+fn to_bignum(n: &BigInt) -> BigNum {
+    BigNum::from_slice(&n.to_bytes_be().1).unwrap()
+}
+
+/// The well known safe prime groups, with 2 as generator, so that larger sizes don't have to be
+/// generated first.
+fn groups() -> Vec<(i32, BigNum, BigNum)> {
+    [
+        BigNum::get_rfc2409_prime_768(),
+        BigNum::get_rfc2409_prime_1024(),
+        BigNum::get_rfc3526_prime_1536(),
+        BigNum::get_rfc3526_prime_2048(),
+        BigNum::get_rfc3526_prime_3072(),
+    ]
+    .into_iter()
+    .map(|p| {
+        let p = p.unwrap();
+        (p.num_bits(), p, BigNum::from_u32(2).unwrap())
+    })
+    .collect()
+}
+
+fn group_generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("group_generation");
+    group.sample_size(10);
+
+    for bits in GENERATED_BITS {
+        group.bench_with_input(
+            BenchmarkId::new("generate_safe_prime", bits),
+            &bits,
+            |b, bits| b.iter(|| generate_safe_prime(*bits).unwrap()),
+        );
+
+        let p = generate_safe_prime(bits).unwrap();
+        group.bench_with_input(
+            BenchmarkId::new("find_cyclic_group_generator", bits),
+            &p,
+            |b, p| b.iter(|| find_cyclic_group_generator(p).unwrap()),
+        );
+    }
+
+    group.finish();
+}
+
+fn prover(c: &mut Criterion) {
+    let mut group = c.benchmark_group("prover");
+
+    for (bits, p, g) in groups() {
+        let (p, g) = (to_bigint(&p), to_bigint(&g));
+        let q = Peggy::order(&g, &p);
+        let x = Peggy::gen_r(&q, &mut thread_rng()).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("calc_c", bits), &bits, |b, _| {
+            b.iter_batched(
+                || Peggy::gen_r(&q, &mut thread_rng()).unwrap(),
+                |r| Peggy::calc_c(&r, &g, &p).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+
+        for choice in [Choice::R, Choice::XRMP] {
+            let name = format!("calc_choice/{:?}", choice);
+            group.bench_with_input(BenchmarkId::new(name, bits), &bits, |b, _| {
+                b.iter_batched(
+                    || Peggy::gen_r(&q, &mut thread_rng()).unwrap(),
+                    |r| Peggy::calc_choice(&choice, &x, &r, &q).unwrap(),
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+    }
+
+    group.finish();
+}
+
+fn verifier(c: &mut Criterion) {
+    let mut group = c.benchmark_group("verifier");
+    let victor = Victor::new();
+
+    for (bits, p_bignum, g_bignum) in groups() {
+        let (p, g) = (to_bigint(&p_bignum), to_bigint(&g_bignum));
+        let q = Peggy::order(&g, &p);
+        let x = Peggy::gen_r(&q, &mut thread_rng()).unwrap();
+        let y = to_bignum(&Peggy::public_key(&x, &g, &p).unwrap());
+
+        let rounds: Vec<(Choice, BigNum, BigNum)> = (0..10)
+            .map(|i| {
+                let choice = if i % 2 == 0 { Choice::R } else { Choice::XRMP };
+                let r = Peggy::gen_r(&q, &mut thread_rng()).unwrap();
+                let c = Peggy::calc_c(&r, &g, &p).unwrap();
+                let res = Peggy::calc_choice(&choice, &x, &r, &q).unwrap();
+
+                (choice, to_bignum(&res), to_bignum(&c))
+            })
+            .collect();
+
+        for (choice, res, c) in &rounds[..2] {
+            let name = format!("verify/{:?}", choice);
+            group.bench_with_input(BenchmarkId::new(name, bits), &bits, |b, _| {
+                b.iter(|| {
+                    victor
+                        .verify(Version::V2, choice, res, c, &y, &g_bignum, &p_bignum)
+                        .unwrap()
+                })
+            });
+        }
+
+        let mut rng = thread_rng();
+        let batch: Vec<Round> = rounds
+            .iter()
+            .map(|(choice, res, c)| Round {
+                choice,
+                res,
+                c,
+                y: &y,
+            })
+            .collect();
+        group.bench_with_input(BenchmarkId::new("verify_batch/10", bits), &bits, |b, _| {
+            b.iter(|| {
+                victor
+                    .verify_batch(Version::V2, &batch, &g_bignum, &p_bignum, &mut rng)
+                    .unwrap()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+/// g^e mod p, the bulk of the work of both sides, with either bignum backend, and from the
+/// table of the powers of `g` the verifier keeps for every group.
+fn modpow(c: &mut Criterion) {
+    let mut group = c.benchmark_group("modpow");
+    let mut bnctx = BigNumContext::new().unwrap();
+
+    for (bits, p_bignum, g_bignum) in groups() {
+        let (p, g) = (to_bigint(&p_bignum), to_bigint(&g_bignum));
+        let e = Peggy::gen_r(&Peggy::order(&g, &p), &mut thread_rng()).unwrap();
+        let e_bignum = to_bignum(&e);
+
+        group.bench_with_input(BenchmarkId::new("num-bigint", bits), &bits, |b, _| {
+            b.iter(|| g.modpow(&e, &p))
+        });
+
+        group.bench_with_input(BenchmarkId::new("openssl", bits), &bits, |b, _| {
+            b.iter(|| {
+                let mut r = BigNum::new().unwrap();
+                r.mod_exp(&g_bignum, &e_bignum, &p_bignum, &mut bnctx)
+                    .unwrap();
+                r
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("fixed-base", bits), &bits, |b, _| {
+            let table = FixedBase::new(&g_bignum, &p_bignum, &mut bnctx)
+                .unwrap()
+                .unwrap();
+            b.iter(|| table.exp(&e_bignum).unwrap().unwrap())
+        });
+
+        group.bench_with_input(BenchmarkId::new("fixed-base/table", bits), &bits, |b, _| {
+            b.iter(|| {
+                FixedBase::new(&g_bignum, &p_bignum, &mut bnctx)
+                    .unwrap()
+                    .unwrap()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, group_generation, prover, verifier, modpow);
+criterion_main!(benches);
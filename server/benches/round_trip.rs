@@ -0,0 +1,146 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use futures::executor::block_on;
+use ntex::web::test::{init_service, read_response_json, TestRequest};
+use ntex::web::{self, App};
+use num_bigint::BigInt;
+use num_traits::Num;
+use pwd_dl_zkp::config::CONFIG;
+use pwd_dl_zkp::router::routes;
+use pwd_dl_zkp_core::core::Choice;
+use pwd_dl_zkp_core::encoding::{self, Width};
+use pwd_dl_zkp_peggy::peggy::Peggy;
+use rand::thread_rng;
+use serde_json::{json, Value};
+
+const USERNAME: &str = "bench";
+
+/// What the client keeps of a session: its id, the group, the order of `g` and the width of its
+/// numbers.
+struct Session {
+    client_id: String,
+    p: BigInt,
+    g: BigInt,
+    q: BigInt,
+    width: Width,
+}
+
+fn number(value: &Value) -> BigInt {
+    BigInt::from_str_radix(value.as_str().unwrap(), 16).unwrap()
+}
+
+fn wire(n: &BigInt, width: Width) -> String {
+    encoding::to_hex(&Peggy::to_wire(n, width).unwrap())
+}
+
+/// The HTTP round-trips of the proof, driven in-process against the whole app: the ntex
+/// overhead, the database transactions and the verification.
+fn round_trip(c: &mut Criterion) {
+    // Before anything opens the database.
+    let db_path = std::env::temp_dir().join(format!("pwd-dl-zkp-bench-{}", std::process::id()));
+    std::env::set_var("ZKP_DB_PATH", &db_path);
+
+    // The in-process service needs no runtime of its own, every request is driven to completion
+    // by its own `block_on`.
+    let app = block_on(init_service(
+        App::new()
+            .app_state(web::types::JsonConfig::default().limit(CONFIG.json_limit))
+            .service(routes()),
+    ));
+    let app = &app;
+
+    let handshake = || async move {
+        let res: Value = read_response_json(
+            app,
+            TestRequest::get()
+                .uri(&format!("/handshake?username={}", USERNAME))
+                .to_request(),
+        )
+        .await;
+        let p = number(&res["p"]);
+        let g = number(&res["g"]);
+
+        Session {
+            client_id: res["clientId"].as_str().unwrap().to_string(),
+            width: Peggy::width(&p),
+            q: Peggy::order(&g, &p),
+            g,
+            p,
+        }
+    };
+
+    let post = |session: &Session, path: &str, body: Value| {
+        TestRequest::post()
+            .uri(path)
+            .header("x-client-id", session.client_id.as_str())
+            .set_json(&body)
+            .to_request()
+    };
+
+    // A closure can't return a future borrowing its arguments.
+    macro_rules! round {
+            ($session:expr, $x:expr) => {{
+                let session: &Session = $session;
+                let r = Peggy::gen_r(&session.q, &mut thread_rng()).unwrap();
+                let c = Peggy::calc_c(&r, &session.g, &session.p).unwrap();
+                let c = json!({ "c": wire(&c, session.width) });
+                let res: Value = read_response_json(app, post(session, "/pick-choice", c)).await;
+                let choice: Choice = serde_json::from_value(res["choice"].clone()).unwrap();
+
+                let res = Peggy::calc_choice(&choice, $x, &r, &session.q).unwrap();
+                let res = json!({ "res": wire(&res, session.width) });
+                let res: Value = read_response_json(app, post(session, "/verify", res)).await;
+
+                res["cont"].as_bool().unwrap()
+            }};
+        }
+
+    let session = block_on(handshake());
+    let x = Peggy::gen_r(&session.q, &mut thread_rng()).unwrap();
+    let y = Peggy::public_key(&x, &session.g, &session.p).unwrap();
+    let _: Value = block_on(read_response_json(
+        app,
+        post(
+            &session,
+            "/public-key",
+            json!({ "y": wire(&y, session.width) }),
+        ),
+    ));
+
+    let mut group = c.benchmark_group("round_trip");
+
+    group.bench_function("handshake", |b| b.iter(|| block_on(handshake())));
+
+    group.bench_function("round", |b| {
+        b.iter_batched(
+            || block_on(handshake()),
+            |session| block_on(async { round!(&session, &x) }),
+            BatchSize::PerIteration,
+        )
+    });
+
+    group.bench_function("login", |b| {
+        b.iter(|| {
+            block_on(async {
+                let session = handshake().await;
+                while round!(&session, &x) {}
+
+                let auth: Value = read_response_json(
+                    app,
+                    TestRequest::get()
+                        .uri("/authenticated")
+                        .header("x-client-id", session.client_id.as_str())
+                        .to_request(),
+                )
+                .await;
+                assert!(auth["auth"].as_bool().unwrap());
+            })
+        })
+    });
+
+    group.finish();
+
+    let _ = std::fs::remove_dir_all(db_path);
+}
+
+criterion_group!(benches, round_trip);
+criterion_main!(benches);
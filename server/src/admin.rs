@@ -0,0 +1,232 @@
+use clap::{Parser, Subcommand};
+use openssl::bn::BigNum;
+use pwd_dl_zkp::audit::{Entry, AUDIT};
+use pwd_dl_zkp::client_data::ClientData;
+use pwd_dl_zkp::config::CONFIG;
+use pwd_dl_zkp::database::{DB, USERS};
+use pwd_dl_zkp::user_data::UserData;
+use pwd_dl_zkp_core::encoding::Width;
+use pwd_dl_zkp_victor::victor::Victor;
+use serde::Serialize;
+use serde_json::from_slice;
+use std::borrow::Borrow;
+use std::fs;
+use std::process::exit;
+
+#[derive(Debug, Parser)]
+#[clap(
+    name = "pwd-dl-zkp-admin",
+    about = "Operate on the pwd-dl-zkp groups and database, the server must not be running"
+)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Generate and validate group parameters
+    #[clap(subcommand)]
+    Group(GroupCommand),
+    /// Inspect and delete client sessions
+    #[clap(subcommand)]
+    Sessions(EntryCommand),
+    /// Inspect and delete registered users
+    #[clap(subcommand)]
+    Users(EntryCommand),
+    /// Print the audit log as JSON lines, oldest first
+    Audit {
+        /// Only print the events of this session
+        #[clap(long)]
+        client_id: Option<String>,
+    },
+    /// Rewrite the database into a fresh one, reclaiming the space of deleted entries
+    Compact,
+    /// Print database statistics as JSON
+    Stats,
+}
+
+#[derive(Debug, Subcommand)]
+enum GroupCommand {
+    /// Generate a safe prime p and a generator g
+    Generate {
+        #[clap(long, default_value_t = 512)]
+        bits: i32,
+    },
+    /// Check that p is a safe prime and that g generates a large subgroup
+    Validate {
+        #[clap(long)]
+        p: String,
+        #[clap(long)]
+        g: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum EntryCommand {
+    /// List the ids of all entries
+    List,
+    /// Print an entry as JSON
+    Inspect { id: String },
+    /// Delete an entry
+    Delete { id: String },
+}
+
+#[derive(Debug, Serialize)]
+struct Group {
+    p: String,
+    g: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Stats {
+    sessions: usize,
+    authenticated_sessions: usize,
+    users: usize,
+    size_on_disk: u64,
+}
+
+fn fail(err: &str) -> ! {
+    eprintln!("error: {}", err);
+    exit(1);
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
+
+fn group(command: GroupCommand) {
+    let victor = Victor::new();
+
+    match command {
+        GroupCommand::Generate { bits } => {
+            let (p, g) = victor.generate_group(bits).unwrap();
+            let width = Width::of_modulus(&p.to_vec());
+
+            print_json(&Group {
+                p: width.to_hex(&p.to_vec()).unwrap(),
+                g: width.to_hex(&g.to_vec()).unwrap(),
+            });
+        }
+        GroupCommand::Validate { p, g } => {
+            let (p, g) = match (BigNum::from_hex_str(&p), BigNum::from_hex_str(&g)) {
+                (Ok(p), Ok(g)) => (p, g),
+                _ => fail("p and g must be hex numbers"),
+            };
+
+            if !victor.validate_group(&p, &g).unwrap() {
+                fail("invalid group");
+            }
+
+            println!("ok");
+        }
+    }
+}
+
+fn sessions(command: EntryCommand) {
+    let db = DB.lock().unwrap();
+
+    match command {
+        EntryCommand::List => db.iter().keys().for_each(|id| {
+            println!("{}", String::from_utf8_lossy(id.unwrap().borrow()));
+        }),
+        EntryCommand::Inspect { id } => match db.get(id.as_str()).unwrap() {
+            Some(data) => print_json(&from_slice::<ClientData>(data.borrow()).unwrap()),
+            None => fail("no such session"),
+        },
+        EntryCommand::Delete { id } => {
+            if db.remove(id.as_str()).unwrap().is_none() {
+                fail("no such session");
+            }
+            db.flush().unwrap();
+        }
+    }
+}
+
+fn users(command: EntryCommand) {
+    match command {
+        EntryCommand::List => USERS.iter().keys().for_each(|username| {
+            println!("{}", String::from_utf8_lossy(username.unwrap().borrow()));
+        }),
+        EntryCommand::Inspect { id } => match USERS.get(id.as_str()).unwrap() {
+            Some(data) => print_json(&from_slice::<UserData>(data.borrow()).unwrap()),
+            None => fail("no such user"),
+        },
+        EntryCommand::Delete { id } => {
+            if USERS.remove(id.as_str()).unwrap().is_none() {
+                fail("no such user");
+            }
+            USERS.flush().unwrap();
+        }
+    }
+}
+
+fn audit(client_id: Option<String>) {
+    AUDIT.iter().values().for_each(|entry| {
+        let entry = entry.unwrap();
+        let matches = client_id.as_ref().is_none_or(|client_id| {
+            &from_slice::<Entry>(entry.borrow()).unwrap().client_id == client_id
+        });
+
+        if matches {
+            println!("{}", String::from_utf8_lossy(entry.borrow()));
+        }
+    });
+}
+
+/// sled has no in-place compaction, so everything is exported into a new database which then
+/// replaces the old one.
+fn compact() {
+    let path = CONFIG.db_path.as_str();
+    let compacted = format!("{}.compact", path);
+    let old = format!("{}.old", path);
+
+    {
+        let db = sled::open(path).unwrap();
+        let new_db = sled::open(compacted.as_str()).unwrap();
+        new_db.import(db.export());
+        new_db.flush().unwrap();
+
+        println!(
+            "{} -> {} bytes",
+            db.size_on_disk().unwrap(),
+            new_db.size_on_disk().unwrap()
+        );
+    }
+
+    fs::rename(path, old.as_str()).unwrap();
+    fs::rename(compacted.as_str(), path).unwrap();
+    fs::remove_dir_all(old.as_str()).unwrap();
+}
+
+fn stats() {
+    let users = USERS.len();
+    let db = DB.lock().unwrap();
+
+    let sessions: Vec<ClientData> = db
+        .iter()
+        .values()
+        .map(|data| from_slice(data.unwrap().borrow()).unwrap())
+        .collect();
+
+    print_json(&Stats {
+        sessions: sessions.len(),
+        authenticated_sessions: sessions
+            .iter()
+            .filter(|data| data.auth.unwrap_or(false))
+            .count(),
+        users,
+        size_on_disk: db.size_on_disk().unwrap(),
+    });
+}
+
+fn main() {
+    match Cli::parse().command {
+        Command::Group(command) => group(command),
+        Command::Sessions(command) => sessions(command),
+        Command::Users(command) => users(command),
+        Command::Audit { client_id } => audit(client_id),
+        Command::Compact => compact(),
+        Command::Stats => stats(),
+    }
+}
@@ -0,0 +1,127 @@
+use crate::config::CONFIG;
+use crate::database::DB;
+use chrono::Utc;
+use ntex::web::HttpRequest;
+use once_cell::sync::Lazy;
+use pwd_dl_zkp_core::core::Choice;
+use pwd_dl_zkp_core::protocol::Version;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+
+/// Append-only log of authentication events, keyed by a big endian monotonic id.
+pub static AUDIT: Lazy<sled::Tree> = Lazy::new(|| DB.lock().unwrap().open_tree("audit").unwrap());
+
+static SINK: Lazy<Option<Mutex<File>>> = Lazy::new(|| {
+    let path = CONFIG.audit_log.as_ref()?;
+
+    match OpenOptions::new().create(true).append(true).open(path) {
+        Ok(file) => Some(Mutex::new(file)),
+        Err(err) => {
+            log::error!("unable to open the audit log {}: {}", path, err);
+            None
+        }
+    }
+});
+
+/// Where a request came from, as far as the server can tell.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Peer {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl From<&HttpRequest> for Peer {
+    fn from(req: &HttpRequest) -> Self {
+        Self {
+            ip: req.peer_addr().map(|addr| addr.ip().to_string()),
+            user_agent: req
+                .headers()
+                .get("user-agent")
+                .and_then(|user_agent| user_agent.to_str().ok())
+                .map(String::from),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    SessionStarted {
+        version: Version,
+        username: Option<String>,
+        registered: bool,
+    },
+    KeyRegistered {
+        username: Option<String>,
+    },
+    RoundStarted {
+        choice: Choice,
+    },
+    Round {
+        choice: Choice,
+        valid: bool,
+    },
+    Result {
+        auth: bool,
+        rounds: usize,
+        failed_rounds: usize,
+        transcript: String,
+    },
+    PasswordChanged {
+        username: String,
+    },
+    TotpEnabled {
+        username: String,
+    },
+    Totp {
+        valid: bool,
+    },
+    /// Recorded under the proof id and without the peer, not to undo the anonymity.
+    MembershipProof {
+        set: String,
+        valid: bool,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Entry {
+    pub at: String,
+    pub client_id: String,
+    #[serde(flatten)]
+    pub peer: Peer,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// Appends `event` to the audit tree and, when `ZKP_AUDIT_LOG` is set, to that JSON-lines file.
+/// Must not be called while holding the `DB` lock.
+pub fn record(client_id: &str, peer: &Peer, event: Event) {
+    let entry = Entry {
+        at: Utc::now().to_rfc3339(),
+        client_id: client_id.to_string(),
+        peer: peer.clone(),
+        event,
+    };
+    let line = serde_json::to_string(&entry).unwrap();
+
+    // Losing an entry must not fail the request it is about.
+    if let Err(err) = store(line.as_str()) {
+        log::error!("unable to store audit entry {}: {}", line, err);
+    }
+
+    if let Some(sink) = SINK.as_ref() {
+        if let Err(err) = writeln!(sink.lock().unwrap(), "{}", line) {
+            log::error!("unable to write audit entry {}: {}", line, err);
+        }
+    }
+}
+
+fn store(line: &str) -> sled::Result<()> {
+    let audit = &*AUDIT;
+    let id = DB.lock().unwrap().generate_id()?;
+    audit.insert(id.to_be_bytes(), line)?;
+
+    Ok(())
+}
@@ -1,21 +1,47 @@
 use pwd_dl_zkp_core::core::Choice;
+use pwd_dl_zkp_core::protocol::Version;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
-pub(crate) struct ClientTest {
+pub struct ClientTest {
     pub c: Option<String>,
     pub choice: Option<Choice>,
     pub valid: Option<bool>,
 }
 
+/// The authentication factors a session has satisfied so far.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct Factors {
+    /// All the rounds of the proof of the password passed.
+    pub zkp: bool,
+    /// A valid TOTP code was sent.
+    pub totp: bool,
+}
+
 #[derive(Clone, Default, Debug, Deserialize, Serialize)]
-pub(crate) struct ClientData {
+pub struct ClientData {
     pub created_at: String,
+    #[serde(default)]
+    pub version: Version,
+    #[serde(default)]
+    pub username: Option<String>,
     pub p: Option<String>,
     pub g: Option<String>,
     pub y: Option<String>,
     pub tests: Vec<ClientTest>,
     pub auth: Option<bool>,
+    /// Whether the user enabled TOTP, the session then needs a code on top of the rounds.
+    #[serde(default)]
+    pub totp_required: bool,
+    #[serde(default)]
+    pub factors: Factors,
+    /// Hex encoded running hash of the group, the public key and every round.
+    #[serde(default)]
+    pub transcript: String,
+    /// The id of the token issued once authenticated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<String>,
 }
 
 impl ClientData {
@@ -26,11 +52,24 @@ impl ClientData {
         }
     }
 
+    /// Whether a round failed, the session can't be authenticated anymore.
+    pub fn failed(&self) -> bool {
+        self.tests.iter().any(|t| t.valid == Some(false))
+    }
+
+    /// Whether every round passed and there were enough of them.
+    pub fn proven(&self) -> bool {
+        !self.failed() && self.valid_rounds() >= 10
+    }
+
     pub fn should_continue(&self) -> bool {
+        !self.failed() && self.valid_rounds() < 10
+    }
+
+    fn valid_rounds(&self) -> usize {
         self.tests
             .iter()
             .filter(|t| t.valid.unwrap_or(false))
             .count()
-            < 10
     }
 }
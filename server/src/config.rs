@@ -0,0 +1,46 @@
+use once_cell::sync::Lazy;
+use openssl::sha::sha256;
+use serde::Serialize;
+use std::env;
+
+#[derive(Debug, Serialize)]
+pub struct Config {
+    pub bind_addr: String,
+    pub allowed_origin: String,
+    pub db_path: String,
+    pub json_limit: usize,
+    pub audit_log: Option<String>,
+    #[serde(skip_serializing)]
+    pub admin_token: Option<String>,
+    #[serde(skip_serializing)]
+    pub token_key: Option<String>,
+}
+
+fn var_or(name: &str, default: &str) -> String {
+    env::var(name).unwrap_or_else(|_| default.to_string())
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        Self {
+            bind_addr: var_or("ZKP_BIND_ADDR", "0.0.0.0:8000"),
+            allowed_origin: var_or("ZKP_ALLOWED_ORIGIN", "http://localhost:3080"),
+            db_path: var_or("ZKP_DB_PATH", "db/client_data"),
+            json_limit: var_or("ZKP_JSON_LIMIT", "1024").parse().unwrap(),
+            audit_log: env::var("ZKP_AUDIT_LOG").ok(),
+            admin_token: env::var("ZKP_ADMIN_TOKEN").ok(),
+            token_key: env::var("ZKP_TOKEN_KEY").ok(),
+        }
+    }
+
+    /// Hex encoded sha256 of the serialized configuration, so that nodes running with different
+    /// settings can be told apart.
+    pub fn fingerprint(&self) -> String {
+        sha256(serde_json::to_string(self).unwrap().as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+}
+
+pub static CONFIG: Lazy<Config> = Lazy::new(Config::from_env);
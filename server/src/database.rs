@@ -1,5 +1,45 @@
+use crate::client_data::ClientData;
+use crate::config::CONFIG;
 use once_cell::sync::Lazy;
+use serde_json::from_slice;
+use std::borrow::Borrow;
 use std::sync::Mutex;
 
-pub(crate) static DB: Lazy<Mutex<sled::Db>> =
-    Lazy::new(|| Mutex::new(sled::open("db/client_data").unwrap()));
+pub static DB: Lazy<Mutex<sled::Db>> =
+    Lazy::new(|| Mutex::new(sled::open(&CONFIG.db_path).unwrap()));
+
+pub static USERS: Lazy<sled::Tree> = Lazy::new(|| DB.lock().unwrap().open_tree("users").unwrap());
+
+pub static SETS: Lazy<sled::Tree> = Lazy::new(|| DB.lock().unwrap().open_tree("sets").unwrap());
+
+pub static MEMBERSHIP_PROOFS: Lazy<sled::Tree> =
+    Lazy::new(|| DB.lock().unwrap().open_tree("membership_proofs").unwrap());
+
+/// The client id of the session every authentication token was issued to, by token id.
+pub static TOKENS: Lazy<sled::Tree> = Lazy::new(|| DB.lock().unwrap().open_tree("tokens").unwrap());
+
+/// The client ids of every session opened for `username`.
+pub fn sessions_of(username: &str) -> Vec<sled::IVec> {
+    DB.lock()
+        .unwrap()
+        .iter()
+        .filter_map(|entry| {
+            let (client_id, data) = entry.ok()?;
+            let data: ClientData = from_slice(data.borrow()).ok()?;
+
+            (data.username.as_deref() == Some(username)).then_some(client_id)
+        })
+        .collect()
+}
+
+/// Removes every session opened for `username`, returning how many were removed.
+pub fn remove_sessions_of(username: &str) -> sled::Result<usize> {
+    let client_ids = sessions_of(username);
+
+    let db = DB.lock().unwrap();
+    for client_id in client_ids.iter() {
+        db.remove(client_id)?;
+    }
+
+    Ok(client_ids.len())
+}
@@ -0,0 +1,14 @@
+pub mod audit;
+pub mod client_data;
+pub mod config;
+pub mod database;
+mod metrics;
+pub mod router;
+pub mod set_data;
+#[cfg(test)]
+mod test_utils;
+mod token;
+mod totp;
+mod transcript;
+pub mod user_data;
+mod web_utils;
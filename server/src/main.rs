@@ -1,33 +1,34 @@
-use crate::router::routes;
 use ntex::web;
 use ntex::web::{middleware, App};
 use ntex_cors::Cors;
-
-mod client_data;
-mod database;
-mod router;
-mod web_utils;
+use once_cell::sync::Lazy;
+use pwd_dl_zkp::config::CONFIG;
+use pwd_dl_zkp::database::DB;
+use pwd_dl_zkp::router::routes;
 
 #[ntex::main]
 async fn main() -> std::io::Result<()> {
     std::env::set_var("RUST_LOG", "actix_web=info");
     env_logger::init();
 
+    // Fail fast if the database cannot be opened, rather than on the first request.
+    Lazy::force(&DB);
+
     web::server(move || {
         App::new()
             .wrap(middleware::Logger::default())
             .wrap(
                 Cors::new()
-                    .allowed_origin("http://localhost:3080")
+                    .allowed_origin(CONFIG.allowed_origin.as_str())
                     .allowed_methods(vec!["GET", "POST", "OPTIONS"])
-                    .allowed_headers(vec!["content-type", "x-client-id"])
+                    .allowed_headers(vec!["authorization", "content-type", "x-client-id"])
                     .max_age(3600)
                     .finish(),
             )
-            .app_state(web::types::JsonConfig::default().limit(1024))
+            .app_state(web::types::JsonConfig::default().limit(CONFIG.json_limit))
             .service(routes())
     })
-    .bind("0.0.0.0:8000")?
+    .bind(CONFIG.bind_addr.as_str())?
     .run()
     .await
 }
@@ -37,9 +38,11 @@ mod tests {
     use num_bigint::BigInt;
     use num_traits::Num;
     use openssl::bn::{BigNum, MsbOption};
+    use pwd_dl_zkp_core::protocol::Version;
     use pwd_dl_zkp_peggy::peggy::Peggy;
     use pwd_dl_zkp_victor::victor::Victor;
-    use rand::thread_rng;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
 
     fn bigint_to_bignum(i: &BigInt) -> BigNum {
         BigNum::from_hex_str(i.to_str_radix(16).as_str()).unwrap()
@@ -51,7 +54,7 @@ mod tests {
 
     #[test]
     fn protocol() {
-        let mut rng = thread_rng();
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
 
         let mut x_bignum = BigNum::new().unwrap();
         x_bignum.rand(32, MsbOption::MAYBE_ZERO, false).unwrap();
@@ -62,6 +65,7 @@ mod tests {
         let (p_bignum, g_bignum) = victor.handshake().unwrap();
         let p_bigint = bignum_to_bigint(&p_bignum);
         let g_bigint = bignum_to_bigint(&g_bignum);
+        let q_bigint = Peggy::order(&g_bigint, &p_bigint);
 
         let y_bigint = Peggy::public_key(&x_bigint, &g_bigint, &p_bigint).unwrap();
         let y_bignum = bigint_to_bignum(&y_bigint);
@@ -69,7 +73,7 @@ mod tests {
         let check = (0..1000)
             .into_iter()
             .map(|_| {
-                let r_bigint = Peggy::gen_r(&p_bigint).unwrap();
+                let r_bigint = Peggy::gen_r(&q_bigint, &mut rng).unwrap();
 
                 let c_bigint = Peggy::calc_c(&r_bigint, &g_bigint, &p_bigint).unwrap();
                 let c_bignum = bigint_to_bignum(&c_bigint);
@@ -77,10 +81,11 @@ mod tests {
                 let choice = victor.pick_choice(&mut rng);
 
                 let res_bigint =
-                    Peggy::calc_choice(&choice, &x_bigint, &r_bigint, &p_bigint).unwrap();
+                    Peggy::calc_choice(&choice, &x_bigint, &r_bigint, &q_bigint).unwrap();
                 let res_bignum = bigint_to_bignum(&res_bigint);
 
                 victor.verify(
+                    Version::V2,
                     &choice,
                     &res_bignum,
                     &c_bignum,
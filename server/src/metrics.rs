@@ -0,0 +1,105 @@
+use ntex::web::{self, HttpResponse};
+use once_cell::sync::Lazy;
+use prometheus::{
+    exponential_buckets, register_histogram, register_histogram_vec, register_int_counter,
+    register_int_counter_vec, register_int_gauge, Histogram, HistogramVec, IntCounter,
+    IntCounterVec, IntGauge,
+};
+use std::future::Future;
+
+pub(crate) static HTTP_REQUESTS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "zkp_http_requests_total",
+        "Number of HTTP requests handled, by route and outcome",
+        &["route", "outcome"]
+    )
+    .unwrap()
+});
+
+pub(crate) static HTTP_REQUEST_DURATION_SECONDS: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "zkp_http_request_duration_seconds",
+        "HTTP request latency, by route",
+        &["route"]
+    )
+    .unwrap()
+});
+
+pub(crate) static HANDSHAKES_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!("zkp_handshakes_total", "Number of completed handshakes").unwrap()
+});
+
+pub(crate) static ROUNDS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "zkp_rounds_total",
+        "Number of protocol rounds started, by picked choice",
+        &["choice"]
+    )
+    .unwrap()
+});
+
+pub(crate) static PROOFS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "zkp_proofs_total",
+        "Number of verified round responses, by outcome",
+        &["outcome"]
+    )
+    .unwrap()
+});
+
+pub(crate) static AUTHENTICATIONS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "zkp_authentications_total",
+        "Number of authentication results, by outcome",
+        &["outcome"]
+    )
+    .unwrap()
+});
+
+pub(crate) static GROUP_GENERATION_SECONDS: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!(
+        "zkp_group_generation_seconds",
+        "Time spent generating a safe prime and its group generator",
+        vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]
+    )
+    .unwrap()
+});
+
+pub(crate) static VERIFY_SECONDS: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!(
+        "zkp_verify_seconds",
+        "Time spent in Victor::verify",
+        exponential_buckets(0.0001, 2.0, 14).unwrap()
+    )
+    .unwrap()
+});
+
+pub(crate) static DB_SIZE_ON_DISK_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "zkp_db_size_on_disk_bytes",
+        "Size of the sled database on disk"
+    )
+    .unwrap()
+});
+
+pub(crate) static DB_ENTRIES: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("zkp_db_entries", "Number of entries in the sled database").unwrap()
+});
+
+pub(crate) async fn observe_route<F>(route: &str, fut: F) -> Result<HttpResponse, web::Error>
+where
+    F: Future<Output = Result<HttpResponse, web::Error>>,
+{
+    let timer = HTTP_REQUEST_DURATION_SECONDS
+        .with_label_values(&[route])
+        .start_timer();
+    let res = fut.await;
+    timer.observe_duration();
+
+    let outcome = if res.is_ok() { "ok" } else { "error" };
+    HTTP_REQUESTS_TOTAL
+        .with_label_values(&[route, outcome])
+        .inc();
+
+    res
+}
@@ -1,20 +1,73 @@
 use ntex::web;
 use ntex::web::DefaultError;
 
+mod admin;
 mod authenticated;
 mod handshake;
 mod healthcheck;
+mod membership;
+mod metrics;
+mod openapi;
+mod password;
 mod pick_choice;
 mod public_key;
+mod readyz;
+mod session;
+mod totp;
 mod verify;
+mod ws;
 
-pub(crate) fn routes() -> web::Scope<DefaultError> {
+pub fn routes() -> web::Scope<DefaultError> {
     web::scope("/").service((
-        healthcheck::routes(),
-        handshake::routes(),
-        public_key::routes(),
-        pick_choice::routes(),
-        verify::routes(),
-        authenticated::routes(),
+        (
+            healthcheck::routes(),
+            healthcheck::livez_routes(),
+            readyz::routes(),
+            metrics::routes(),
+            openapi::routes(),
+        ),
+        (
+            handshake::routes(),
+            public_key::routes(),
+            pick_choice::routes(),
+            verify::routes(),
+            authenticated::routes(),
+            session::routes(),
+            password::routes(),
+            totp::routes(),
+            membership::routes(),
+            ws::routes(),
+        ),
+        admin::routes(),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::server;
+    use pwd_dl_zkp_client::client::{Client, Error};
+
+    /// The Rust client registers and logs in against the whole app.
+    #[ntex::test]
+    async fn client() {
+        let srv = server();
+        let client = Client::new(&srv.url("/"));
+
+        let registered = client.register("client", "correct horse").await.unwrap();
+        assert!(registered.token.is_none());
+        assert!(matches!(
+            client.register("client", "battery staple").await,
+            Err(Error::AlreadyRegistered)
+        ));
+
+        let session = client.login("client", "correct horse").await.unwrap();
+        assert!(session.rounds.iter().all(|round| round.valid));
+        assert!(session.transcript.is_some());
+        assert!(session.token.is_some());
+
+        assert!(matches!(
+            client.login("unknown", "correct horse").await,
+            Err(Error::NotRegistered)
+        ));
+    }
+}
@@ -0,0 +1,524 @@
+use crate::client_data::ClientData;
+use crate::config::CONFIG;
+use crate::database::{remove_sessions_of, DB, SETS, USERS};
+use crate::metrics::observe_route;
+use crate::router::handshake::{new_client_id, new_group};
+use crate::set_data::SetData;
+use crate::user_data::{enrollment_digest, UserData};
+use crate::web_utils::JsonErr;
+use ntex::http::{Response as HttpResponse, StatusCode};
+use ntex::web;
+use ntex::web::DefaultError;
+use openssl::memcmp;
+use serde::Serialize;
+use serde_json::from_slice;
+use sled::transaction::{TransactionError, TransactionResult};
+use std::borrow::Borrow;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = AdminSession)]
+pub(crate) struct Session {
+    #[serde(rename = "clientId")]
+    client_id: String,
+    #[schema(value_type = Object)]
+    data: ClientData,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct ReenrollResponse {
+    /// To hand to the user out of band, it is sent along the new key to `/public-key`.
+    code: String,
+}
+
+/// Admin routes are only enabled when `ZKP_ADMIN_TOKEN` is set, and expect it as a bearer token.
+fn check_token(req: &web::HttpRequest) -> Result<(), JsonErr> {
+    let expected = CONFIG
+        .admin_token
+        .as_ref()
+        .ok_or(JsonErr::new(StatusCode::FORBIDDEN, "admin API is disabled"))?;
+
+    let token = req
+        .headers()
+        .get("authorization")
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .ok_or(JsonErr::new(
+            StatusCode::UNAUTHORIZED,
+            "authorization header not present or not valid",
+        ))?;
+
+    if token.len() != expected.len() || !memcmp::eq(token.as_bytes(), expected.as_bytes()) {
+        return Err(JsonErr::new(
+            StatusCode::UNAUTHORIZED,
+            "invalid admin token",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Every stored session, skipping the entries that aren't one, such as a corrupt session.
+fn do_list_sessions() -> Vec<Session> {
+    DB.lock()
+        .unwrap()
+        .iter()
+        .filter_map(|entry| {
+            let (client_id, data) = entry.ok()?;
+
+            Some(Session {
+                client_id: String::from_utf8_lossy(client_id.borrow()).to_string(),
+                data: from_slice(data.borrow()).ok()?,
+            })
+        })
+        .collect()
+}
+
+fn do_revoke_session(client_id: &str) -> Result<(), JsonErr> {
+    match DB.lock().unwrap().remove(client_id).unwrap() {
+        Some(_) => Ok(()),
+        None => Err(JsonErr::new(StatusCode::NOT_FOUND, "no such session")),
+    }
+}
+
+/// Applies `update` to the stored user, then drops all of their sessions if `revoke` is set.
+fn do_update_user<F>(username: &str, revoke: bool, update: F) -> Result<(), JsonErr>
+where
+    F: Fn(&mut UserData),
+{
+    let tx_res: TransactionResult<(), JsonErr> = USERS.transaction(|tx_users| {
+        let mut user: UserData = match tx_users.get(username).unwrap() {
+            Some(user) => from_slice(user.borrow()).unwrap(),
+            None => {
+                return sled::transaction::abort(JsonErr::new(
+                    StatusCode::NOT_FOUND,
+                    "no such user",
+                ))
+            }
+        };
+
+        update(&mut user);
+
+        tx_users
+            .insert(username, serde_json::to_string(&user).unwrap().as_str())
+            .unwrap();
+
+        Ok(())
+    });
+
+    tx_res.map_err(|err| match err {
+        TransactionError::Abort(err) => err,
+        TransactionError::Storage(_) => {
+            JsonErr::new(StatusCode::INTERNAL_SERVER_ERROR, "unable to update user")
+        }
+    })?;
+
+    if revoke {
+        remove_sessions_of(username).or(Err(JsonErr::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to revoke sessions",
+        )))?;
+    }
+
+    Ok(())
+}
+
+/// Draws the one-time code the user must present to register a new key, only its digest is
+/// kept, and drops all of their sessions.
+fn do_reenroll_user(username: &str) -> Result<ReenrollResponse, JsonErr> {
+    let code = new_client_id().or(Err(JsonErr::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "unable to draw an enrollment code",
+    )))?;
+    let digest = enrollment_digest(code.as_str());
+
+    do_update_user(username, true, |user| {
+        user.enrollment = Some(digest.clone())
+    })?;
+
+    Ok(ReenrollResponse { code })
+}
+
+fn do_create_set(name: &str) -> Result<(), JsonErr> {
+    let valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid_name {
+        return Err(JsonErr::new(
+            StatusCode::BAD_REQUEST,
+            "set names can only contain letters, digits, - and _",
+        ));
+    }
+
+    let (p, g) = new_group().or(Err(JsonErr::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "unable to generate the group",
+    )))?;
+    let data = serde_json::to_string(&SetData::new(p.as_str(), g.as_str())).unwrap();
+
+    SETS.compare_and_swap(name, None as Option<&[u8]>, Some(data.as_str()))
+        .unwrap()
+        .or(Err(JsonErr::new(
+            StatusCode::CONFLICT,
+            "set already exists",
+        )))
+}
+
+/// Applies `update` to the stored set, `update` fails with the error to respond with.
+fn do_update_set<F>(name: &str, update: F) -> Result<(), JsonErr>
+where
+    F: Fn(&mut SetData) -> Result<(), JsonErr>,
+{
+    let tx_res: TransactionResult<(), JsonErr> = SETS.transaction(|tx_sets| {
+        let mut data: SetData = match tx_sets.get(name).unwrap() {
+            Some(data) => from_slice(data.borrow()).unwrap(),
+            None => {
+                return sled::transaction::abort(JsonErr::new(StatusCode::NOT_FOUND, "no such set"))
+            }
+        };
+
+        if let Err(err) = update(&mut data) {
+            return sled::transaction::abort(err);
+        }
+
+        tx_sets
+            .insert(name, serde_json::to_string(&data).unwrap().as_str())
+            .unwrap();
+
+        Ok(())
+    });
+
+    tx_res.map_err(|err| match err {
+        TransactionError::Abort(err) => err,
+        TransactionError::Storage(_) => {
+            JsonErr::new(StatusCode::INTERNAL_SERVER_ERROR, "unable to update set")
+        }
+    })
+}
+
+fn do_add_member(name: &str, username: &str) -> Result<(), JsonErr> {
+    if !USERS.contains_key(username).unwrap() {
+        return Err(JsonErr::new(StatusCode::NOT_FOUND, "no such user"));
+    }
+
+    do_update_set(name, |data| {
+        data.members.entry(username.to_string()).or_insert(None);
+
+        Ok(())
+    })
+}
+
+fn do_remove_member(name: &str, username: &str) -> Result<(), JsonErr> {
+    do_update_set(name, |data| {
+        data.members
+            .remove(username)
+            .map(|_| ())
+            .ok_or(JsonErr::new(StatusCode::NOT_FOUND, "no such member"))
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/sessions",
+    params(("authorization" = String, Header, description = "Bearer admin token")),
+    responses(
+        (status = 200, body = [AdminSession]),
+        (status = 401, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+    ),
+)]
+#[web::get("/sessions")]
+async fn list_sessions(req: web::HttpRequest) -> Result<HttpResponse, web::Error> {
+    observe_route("/admin/sessions", async move {
+        check_token(&req)?;
+
+        Ok::<_, web::Error>(HttpResponse::Ok().json(&do_list_sessions()))
+    })
+    .await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/sessions/{client_id}",
+    params(
+        ("authorization" = String, Header, description = "Bearer admin token"),
+        ("client_id" = String, Path, description = "Session to revoke"),
+    ),
+    responses(
+        (status = 200, description = "The session has been revoked"),
+        (status = 401, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+    ),
+)]
+#[web::delete("/sessions/{client_id}")]
+async fn revoke_session(
+    req: web::HttpRequest,
+    client_id: web::types::Path<String>,
+) -> Result<HttpResponse, web::Error> {
+    observe_route("/admin/sessions/{client_id}", async move {
+        check_token(&req)?;
+
+        Ok::<_, web::Error>(HttpResponse::Ok().json(&do_revoke_session(client_id.as_str())?))
+    })
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{username}/lock",
+    params(
+        ("authorization" = String, Header, description = "Bearer admin token"),
+        ("username" = String, Path, description = "User to lock out, their sessions are revoked"),
+    ),
+    responses(
+        (status = 200, description = "The user has been locked"),
+        (status = 401, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+    ),
+)]
+#[web::post("/users/{username}/lock")]
+async fn lock_user(
+    req: web::HttpRequest,
+    username: web::types::Path<String>,
+) -> Result<HttpResponse, web::Error> {
+    observe_route("/admin/users/{username}/lock", async move {
+        check_token(&req)?;
+
+        Ok::<_, web::Error>(HttpResponse::Ok().json(&do_update_user(
+            username.as_str(),
+            true,
+            |user| user.locked = true,
+        )?))
+    })
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{username}/unlock",
+    params(
+        ("authorization" = String, Header, description = "Bearer admin token"),
+        ("username" = String, Path, description = "User to let back in"),
+    ),
+    responses(
+        (status = 200, description = "The user has been unlocked and their wrong TOTP codes forgotten"),
+        (status = 401, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+    ),
+)]
+#[web::post("/users/{username}/unlock")]
+async fn unlock_user(
+    req: web::HttpRequest,
+    username: web::types::Path<String>,
+) -> Result<HttpResponse, web::Error> {
+    observe_route("/admin/users/{username}/unlock", async move {
+        check_token(&req)?;
+
+        Ok::<_, web::Error>(HttpResponse::Ok().json(&do_update_user(
+            username.as_str(),
+            false,
+            |user| {
+                user.locked = false;
+                if let Some(totp) = user.totp.as_mut() {
+                    totp.failed_attempts = 0;
+                }
+            },
+        )?))
+    })
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{username}/reenroll",
+    params(
+        ("authorization" = String, Header, description = "Bearer admin token"),
+        ("username" = String, Path, description = "User that must register a new public key"),
+    ),
+    responses(
+        (status = 200, description = "The next registration of the user with the code replaces their key", body = ReenrollResponse),
+        (status = 401, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+    ),
+)]
+#[web::post("/users/{username}/reenroll")]
+async fn reenroll_user(
+    req: web::HttpRequest,
+    username: web::types::Path<String>,
+) -> Result<HttpResponse, web::Error> {
+    observe_route("/admin/users/{username}/reenroll", async move {
+        check_token(&req)?;
+
+        Ok::<_, web::Error>(HttpResponse::Ok().json(&do_reenroll_user(username.as_str())?))
+    })
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/sets/{name}",
+    params(
+        ("authorization" = String, Header, description = "Bearer admin token"),
+        ("name" = String, Path, description = "Set to create, with a group of its own"),
+    ),
+    responses(
+        (status = 200, description = "The set has been created"),
+        (status = 400, body = ErrorResponse),
+        (status = 401, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+        (status = 409, body = ErrorResponse),
+    ),
+)]
+#[web::post("/sets/{name}")]
+async fn create_set(
+    req: web::HttpRequest,
+    name: web::types::Path<String>,
+) -> Result<HttpResponse, web::Error> {
+    observe_route("/admin/sets/{name}", async move {
+        check_token(&req)?;
+
+        Ok::<_, web::Error>(HttpResponse::Ok().json(&do_create_set(name.as_str())?))
+    })
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/sets/{name}/members/{username}",
+    params(
+        ("authorization" = String, Header, description = "Bearer admin token"),
+        ("name" = String, Path, description = "Set to add the user to"),
+        ("username" = String, Path, description = "User allowed to enroll a key in the set"),
+    ),
+    responses(
+        (status = 200, description = "The user has been added"),
+        (status = 401, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+    ),
+)]
+#[web::post("/sets/{name}/members/{username}")]
+async fn add_member(
+    req: web::HttpRequest,
+    path: web::types::Path<(String, String)>,
+) -> Result<HttpResponse, web::Error> {
+    observe_route("/admin/sets/{name}/members/{username}", async move {
+        check_token(&req)?;
+
+        Ok::<_, web::Error>(
+            HttpResponse::Ok().json(&do_add_member(path.0.as_str(), path.1.as_str())?),
+        )
+    })
+    .await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/sets/{name}/members/{username}",
+    params(
+        ("authorization" = String, Header, description = "Bearer admin token"),
+        ("name" = String, Path, description = "Set to remove the user from"),
+        ("username" = String, Path, description = "Member to remove, along with their key"),
+    ),
+    responses(
+        (status = 200, description = "The member has been removed"),
+        (status = 401, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+    ),
+)]
+#[web::delete("/sets/{name}/members/{username}")]
+async fn remove_member(
+    req: web::HttpRequest,
+    path: web::types::Path<(String, String)>,
+) -> Result<HttpResponse, web::Error> {
+    observe_route("/admin/sets/{name}/members/{username}", async move {
+        check_token(&req)?;
+
+        Ok::<_, web::Error>(
+            HttpResponse::Ok().json(&do_remove_member(path.0.as_str(), path.1.as_str())?),
+        )
+    })
+    .await
+}
+
+pub(crate) fn routes() -> web::Scope<DefaultError> {
+    web::scope("/admin").service((
+        list_sessions,
+        revoke_session,
+        lock_user,
+        unlock_user,
+        reenroll_user,
+        create_set,
+        add_member,
+        remove_member,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::DB;
+    use crate::test_utils::{admin_post, app, handshake, post, register, wire, ADMIN_TOKEN};
+    use ntex::http::StatusCode;
+    use ntex::web::test::{call_service, read_response_json, TestRequest};
+    use pwd_dl_zkp_peggy::peggy::Peggy;
+    use rand::thread_rng;
+    use serde_json::{json, Value};
+
+    /// Anyone can open a session for a re-enrolled user, only the code lets them replace the key,
+    /// and only once.
+    #[ntex::test]
+    async fn reenroll() {
+        let app = app().await;
+        register(&app, "reenroll").await;
+
+        let res: Value =
+            read_response_json(&app, admin_post("/admin/users/reenroll/reenroll")).await;
+        let code = res["code"].as_str().unwrap();
+
+        for (enrollment, status) in [
+            (None, StatusCode::FORBIDDEN),
+            (Some("0".repeat(code.len())), StatusCode::FORBIDDEN),
+            (Some(code.to_string()), StatusCode::OK),
+            (Some(code.to_string()), StatusCode::CONFLICT),
+        ] {
+            let session = handshake(&app, "reenroll").await;
+            let x = Peggy::gen_r(&session.q, &mut thread_rng()).unwrap();
+            let y = Peggy::public_key(&x, &session.g, &session.p).unwrap();
+            let body = json!({ "y": wire(&y, session.width), "enrollment": enrollment });
+
+            let res = call_service(&app, post(&session, "/public-key", body)).await;
+            assert_eq!(res.status(), status, "{:?}", enrollment);
+        }
+    }
+
+    /// Entries that aren't sessions are left out of the list rather than failing it.
+    #[ntex::test]
+    async fn list_sessions() {
+        let app = app().await;
+        let session = handshake(&app, "listed").await;
+        DB.lock()
+            .unwrap()
+            .insert("not-a-session", "{".as_bytes())
+            .unwrap();
+
+        let req = TestRequest::get()
+            .uri("/admin/sessions")
+            .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+            .to_request();
+        let sessions: Vec<Value> = read_response_json(&app, req).await;
+        DB.lock().unwrap().remove("not-a-session").unwrap();
+
+        assert!(sessions
+            .iter()
+            .any(|listed| listed["clientId"] == session.client_id));
+        assert!(sessions
+            .iter()
+            .all(|listed| listed["clientId"] != "not-a-session"));
+    }
+}
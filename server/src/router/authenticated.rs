@@ -1,68 +1,215 @@
-use crate::client_data::ClientData;
-use crate::database::DB;
-use crate::web_utils::{DbTxErr, JsonErr};
+use crate::audit::{self, Event, Peer};
+use crate::client_data::{ClientData, Factors};
+use crate::database::{DB, TOKENS};
+use crate::metrics::{observe_route, AUTHENTICATIONS_TOTAL};
+use crate::router::handshake::new_client_id;
+use crate::token;
+use crate::web_utils::{client_id, DbTxErr, JsonErr};
 use ntex::http::{Response as HttpResponse, StatusCode};
 use ntex::web;
 use ntex::web::DefaultError;
 use serde::Serialize;
 use serde_json::from_slice;
-use sled::transaction::TransactionResult;
+use sled::transaction::{TransactionError, TransactionResult, Transactional};
 use std::borrow::Borrow;
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize)]
-struct Response {
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = AuthenticatedResponse)]
+pub(crate) struct Response {
+    /// Whether every factor the user needs is satisfied.
     auth: bool,
+    factors: Factors,
+    /// Hex encoded hash of the whole proof transcript.
+    #[schema(pattern = "^[0-9a-f]+$")]
+    transcript: String,
+    /// Opaque bearer token of this session, only issued on success.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
 }
 
 impl Response {
-    pub fn new(auth: bool) -> Self {
-        Self { auth }
+    pub fn new(auth: bool, factors: Factors, transcript: &str, token: Option<String>) -> Self {
+        Self {
+            auth,
+            factors,
+            transcript: transcript.to_string(),
+            token,
+        }
     }
 }
 
-fn do_authenticated(client_id: &str) -> Result<Response, DbTxErr> {
-    let tx_res: TransactionResult<bool, DbTxErr> = DB.lock().unwrap().transaction(|tx_db| {
-        let mut data: ClientData =
-            from_slice(tx_db.get(client_id).unwrap().unwrap().borrow()).unwrap();
+/// What a session settled on, `fresh` when settled by this very call.
+struct Outcome {
+    fresh: bool,
+    auth: bool,
+    factors: Factors,
+    rounds: usize,
+    failed_rounds: usize,
+    transcript: String,
+    token_id: Option<String>,
+}
+
+pub(crate) fn do_authenticated(client_id: &str, peer: &Peer) -> Result<Response, DbTxErr> {
+    let token_id = new_client_id().or(Err(DbTxErr::new("unable to draw a token id")))?;
+
+    let tokens = &*TOKENS;
+    let db = DB.lock().unwrap();
+    let tx_res: TransactionResult<Outcome, DbTxErr> =
+        (&**db, tokens).transaction(|(tx_db, tx_tokens)| {
+            let mut data: ClientData = match tx_db.get(client_id).unwrap() {
+                Some(data) => from_slice(data.borrow()).unwrap(),
+                None => return sled::transaction::abort(DbTxErr::new("no such session")),
+            };
+
+            // Asking again answers with the outcome already settled, token included.
+            if let Some(auth) = data.auth {
+                return Ok(Outcome {
+                    fresh: false,
+                    auth,
+                    factors: data.factors,
+                    rounds: 0,
+                    failed_rounds: 0,
+                    transcript: data.transcript,
+                    token_id: data.token_id,
+                });
+            }
+
+            data.factors.zkp = data.proven();
+            let auth = data.factors.zkp && (data.factors.totp || !data.totp_required);
+            let rounds = data.tests.len();
+            let failed_rounds = data
+                .tests
+                .iter()
+                .filter(|test| test.valid == Some(false))
+                .count();
+
+            data.auth = Some(auth);
+            data.tests = vec![];
+
+            if auth {
+                data.token_id = Some(token_id.clone());
+                tx_tokens.insert(token_id.as_str(), client_id).unwrap();
+            }
+
+            tx_db
+                .insert(
+                    client_id.clone(),
+                    serde_json::to_string(&data).unwrap().as_str(),
+                )
+                .unwrap();
 
-        let auth = !data.should_continue();
+            Ok(Outcome {
+                fresh: true,
+                auth,
+                factors: data.factors,
+                rounds,
+                failed_rounds,
+                transcript: data.transcript.clone(),
+                token_id: data.token_id.clone(),
+            })
+        });
+    drop(db);
 
-        data.auth = Some(auth);
-        data.tests = vec![];
+    let outcome = tx_res.map_err(|err| match err {
+        TransactionError::Abort(err) => err,
+        TransactionError::Storage(_) => DbTxErr::new("unable to store the outcome"),
+    })?;
 
-        tx_db
-            .insert(
-                client_id.clone(),
-                serde_json::to_string(&data).unwrap().as_str(),
-            )
-            .unwrap();
+    if outcome.fresh {
+        AUTHENTICATIONS_TOTAL
+            .with_label_values(&[if outcome.auth { "success" } else { "failure" }])
+            .inc();
+        audit::record(
+            client_id,
+            peer,
+            Event::Result {
+                auth: outcome.auth,
+                rounds: outcome.rounds,
+                failed_rounds: outcome.failed_rounds,
+                transcript: outcome.transcript.clone(),
+            },
+        );
+    }
 
-        Ok(auth)
-    });
+    let token = outcome
+        .token_id
+        .as_deref()
+        .map(|token_id| token::issue(token_id, &outcome.transcript));
 
-    Ok(Response::new(tx_res.unwrap()))
+    Ok(Response::new(
+        outcome.auth,
+        outcome.factors,
+        &outcome.transcript,
+        token,
+    ))
 }
 
+#[utoipa::path(
+    get,
+    path = "/authenticated",
+    params(("x-client-id" = String, Header, description = "Client id returned by /handshake")),
+    responses(
+        (status = 200, body = AuthenticatedResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
+    ),
+)]
 #[web::get("")]
 async fn authenticated(req: web::HttpRequest) -> Result<HttpResponse, web::Error> {
-    let client_id = req
-        .headers()
-        .get("x-client-id")
-        .unwrap()
-        .to_str()
-        .or(Err(JsonErr::new(
-            StatusCode::BAD_REQUEST,
-            "x-client-id header not present or not valid",
-        )))?;
-
-    Ok(
-        HttpResponse::Ok().json(&do_authenticated(client_id).or(Err(JsonErr::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "unable to authenticate",
-        )))?),
-    )
+    observe_route("/authenticated", async move {
+        let client_id = client_id(&req)?;
+
+        Ok::<_, web::Error>(HttpResponse::Ok().json(
+            &do_authenticated(client_id, &Peer::from(&req)).or(Err(JsonErr::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to authenticate",
+            )))?,
+        ))
+    })
+    .await
 }
 
 pub(crate) fn routes() -> web::Scope<DefaultError> {
     web::scope("/authenticated").service(authenticated)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{app, get, login, register};
+    use ntex::http::StatusCode;
+    use ntex::web::test::{call_service, read_response_json, TestRequest};
+    use serde_json::Value;
+
+    /// Asking twice doesn't revoke the token, which gives nothing of the session away but the
+    /// transcript it is bound to.
+    #[ntex::test]
+    async fn settled_once() {
+        let app = app().await;
+        let (session, x) = register(&app, "authenticated").await;
+        let first = login(&app, &session, &x).await;
+        let again: Value = read_response_json(&app, get(&session, "/authenticated")).await;
+        assert_eq!(first["auth"], true);
+        assert_eq!(first, again);
+
+        let token = first["token"].as_str().unwrap();
+        assert!(!token.contains(session.client_id.as_str()));
+        let transcript = first["transcript"].as_str().unwrap();
+        assert!(token.contains(transcript));
+
+        let bearer = |token: &str| {
+            TestRequest::get()
+                .uri("/session")
+                .header("authorization", format!("Bearer {}", token))
+                .to_request()
+        };
+        let res: Value = read_response_json(&app, bearer(token)).await;
+        assert_eq!(res["clientId"], session.client_id);
+
+        let forged = token.replace(transcript, &"0".repeat(transcript.len()));
+        assert_eq!(
+            call_service(&app, bearer(&forged)).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
@@ -1,46 +1,119 @@
+use crate::audit::{self, Event, Peer};
 use crate::client_data::ClientData;
-use crate::database::DB;
-use crate::web_utils::JsonErr;
+use crate::database::{DB, USERS};
+use crate::metrics::{observe_route, GROUP_GENERATION_SECONDS, HANDSHAKES_TOTAL};
+use crate::transcript;
+use crate::user_data::UserData;
+use crate::web_utils::{stored_to_wire, to_stored, width_of, JsonErr};
 use ntex::http::{Response as HttpResponse, StatusCode};
 use ntex::web;
 use ntex::web::DefaultError;
 use openssl::bn::{BigNum, MsbOption};
 use openssl::error::ErrorStack;
+use pwd_dl_zkp_core::encoding;
+use pwd_dl_zkp_core::protocol::Version;
 use pwd_dl_zkp_victor::victor::Victor;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use serde_json::from_slice;
+use std::borrow::Borrow;
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Debug, Serialize)]
-struct Response {
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct Query {
+    /// Register or log in as this user; without it the session is anonymous.
+    username: Option<String>,
+    /// Comma separated protocol versions the client speaks, most preferred first;
+    /// without it the client is assumed to speak `pwd-dl-zkp/1`.
+    versions: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = HandshakeResponse)]
+pub(crate) struct Response {
     #[serde(rename = "clientId")]
-    client_id: String,
-    p: String,
-    g: String,
+    pub client_id: String,
+    /// The protocol version the rest of the session speaks.
+    version: Version,
+    #[serde(with = "encoding::hex")]
+    #[schema(value_type = String, pattern = "^([0-9a-f]{2})+$")]
+    p: Vec<u8>,
+    /// As wide as `p`, like every number of the session.
+    #[serde(with = "encoding::hex")]
+    #[schema(value_type = String, pattern = "^([0-9a-f]{2})+$")]
+    g: Vec<u8>,
+    /// Whether the user already has a public key, in which case `/public-key` must be skipped.
+    registered: bool,
+    /// Whether a code must be sent to `/totp/verify` before `/authenticated`.
+    totp: bool,
 }
 
 impl Response {
-    pub fn new(client_id: &str, p: &str, g: &str) -> Self {
+    pub fn new(
+        client_id: &str,
+        version: Version,
+        p: Vec<u8>,
+        g: Vec<u8>,
+        registered: bool,
+        totp: bool,
+    ) -> Self {
         Self {
             client_id: client_id.to_string(),
-            p: p.to_string(),
-            g: g.to_string(),
+            version,
+            p,
+            g,
+            registered,
+            totp,
         }
     }
 }
 
-fn do_handshake() -> Result<Response, ErrorStack> {
-    let mut client_id = BigNum::new()?;
-    client_id.rand(128, MsbOption::MAYBE_ZERO, false)?;
-    let client_id = client_id.to_hex_str()?.to_string().to_lowercase();
+pub(crate) fn do_handshake(
+    username: Option<&str>,
+    versions: &[String],
+    peer: &Peer,
+) -> Result<Response, JsonErr> {
+    let user: Option<UserData> = username
+        .and_then(|username| USERS.get(username).unwrap())
+        .map(|user| from_slice(user.borrow()).unwrap());
 
-    let victor = Victor::new();
-    let (p, g) = victor.handshake()?;
-    let p = p.to_hex_str()?.to_string();
-    let g = g.to_hex_str()?.to_string();
+    if user.as_ref().is_some_and(|user| user.locked) {
+        return Err(JsonErr::new(StatusCode::FORBIDDEN, "user is locked"));
+    }
+    let user = user.filter(|user| user.enrollment.is_none());
+    let version = negotiate_version(versions, user.as_ref())?;
+
+    let handshake_err = || JsonErr::new(StatusCode::INTERNAL_SERVER_ERROR, "unable to handshake");
+    let client_id = new_client_id().or(Err(handshake_err()))?;
 
     let mut data = ClientData::new();
+    data.version = version;
+    data.username = username.map(String::from);
+
+    let (p, g) = match user.as_ref() {
+        Some(user) => {
+            data.y = Some(user.y.clone());
+            data.totp_required = user.totp_required();
+
+            (user.p.clone(), user.g.clone())
+        }
+        None => new_group().or(Err(handshake_err()))?,
+    };
     data.p = Some(p.clone());
     data.g = Some(g.clone());
 
+    let width = width_of(&BigNum::from_hex_str(&p).unwrap());
+
+    data.transcript = transcript::start(
+        version,
+        &BigNum::from_hex_str(&p).unwrap(),
+        &BigNum::from_hex_str(&g).unwrap(),
+    );
+    if let Some(y) = data.y.as_deref() {
+        data.transcript =
+            transcript::public_key(&data.transcript, width, &BigNum::from_hex_str(y).unwrap());
+    }
+
     DB.lock()
         .unwrap()
         .insert(
@@ -49,15 +122,101 @@ fn do_handshake() -> Result<Response, ErrorStack> {
         )
         .unwrap();
 
-    Ok(Response::new(client_id.as_str(), p.as_str(), g.as_str()))
+    HANDSHAKES_TOTAL.inc();
+    audit::record(
+        client_id.as_str(),
+        peer,
+        Event::SessionStarted {
+            version,
+            username: data.username.clone(),
+            registered: user.is_some(),
+        },
+    );
+
+    Ok(Response::new(
+        client_id.as_str(),
+        version,
+        stored_to_wire(&p, width),
+        stored_to_wire(&g, width),
+        user.is_some(),
+        data.totp_required,
+    ))
 }
 
+/// A registered user can only log in with the version its key was registered with.
+fn negotiate_version(versions: &[String], user: Option<&UserData>) -> Result<Version, JsonErr> {
+    let legacy = [Version::default().as_str().to_string()];
+    let versions: &[String] = if versions.is_empty() {
+        &legacy
+    } else {
+        versions
+    };
+
+    match user {
+        Some(user) => versions
+            .iter()
+            .any(|version| version.trim() == user.version.as_str())
+            .then_some(user.version)
+            .ok_or(JsonErr::new(
+                StatusCode::CONFLICT,
+                "user registered with a protocol version not offered",
+            )),
+        None => Version::negotiate(versions).ok_or(JsonErr::new(
+            StatusCode::BAD_REQUEST,
+            "no supported protocol version offered",
+        )),
+    }
+}
+
+pub(crate) fn new_client_id() -> Result<String, ErrorStack> {
+    let mut client_id = BigNum::new()?;
+    client_id.rand(128, MsbOption::MAYBE_ZERO, false)?;
+
+    Ok(client_id.to_hex_str()?.to_string().to_lowercase())
+}
+
+pub(crate) fn new_group() -> Result<(String, String), ErrorStack> {
+    let victor = Victor::new();
+    let timer = GROUP_GENERATION_SECONDS.start_timer();
+    let (p, g) = victor.handshake()?;
+    timer.observe_duration();
+
+    let width = width_of(&p);
+
+    Ok((to_stored(&p, width), to_stored(&g, width)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/handshake",
+    params(Query),
+    responses(
+        (status = 200, body = HandshakeResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 403, description = "The user is locked", body = ErrorResponse),
+        (status = 409, description = "The user registered with a version not offered", body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
+    ),
+)]
 #[web::get("")]
-async fn handshake() -> Result<HttpResponse, web::Error> {
-    Ok(HttpResponse::Ok().json(&do_handshake().or(Err(JsonErr::new(
-        StatusCode::INTERNAL_SERVER_ERROR,
-        "unable to handshake",
-    )))?))
+async fn handshake(
+    req: web::HttpRequest,
+    query: web::types::Query<Query>,
+) -> Result<HttpResponse, web::Error> {
+    observe_route("/handshake", async move {
+        let versions: Vec<String> = query
+            .versions
+            .as_deref()
+            .map(|versions| versions.split(',').map(String::from).collect())
+            .unwrap_or_default();
+
+        Ok::<_, web::Error>(HttpResponse::Ok().json(&do_handshake(
+            query.username.as_deref(),
+            &versions,
+            &Peer::from(&req),
+        )?))
+    })
+    .await
 }
 
 pub(crate) fn routes() -> web::Scope<DefaultError> {
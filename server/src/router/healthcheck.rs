@@ -1,9 +1,12 @@
 use ntex::web;
 use ntex::web::{DefaultError, HttpResponse};
 use serde::Serialize;
+use utoipa::openapi::OpenApi;
+use utoipa::{Modify, ToSchema};
 
-#[derive(Debug, Serialize)]
-struct Response {
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = HealthcheckResponse)]
+pub(crate) struct Response {
     ok: bool,
 }
 
@@ -13,11 +16,30 @@ impl Response {
     }
 }
 
+#[utoipa::path(get, path = "/healthcheck", responses((status = 200, body = HealthcheckResponse)))]
 #[web::get("")]
 async fn healthcheck() -> HttpResponse {
     HttpResponse::Ok().json(&Response::new(true))
 }
 
+/// Documents /livez, the liveness probe answered by the same handler.
+pub(crate) struct Livez;
+
+impl Modify for Livez {
+    fn modify(&self, openapi: &mut OpenApi) {
+        if let Some(mut item) = openapi.paths.paths.get("/healthcheck").cloned() {
+            for operation in item.operations.values_mut() {
+                operation.operation_id = Some("livez".to_string());
+            }
+            openapi.paths.paths.insert("/livez".to_string(), item);
+        }
+    }
+}
+
 pub(crate) fn routes() -> web::Scope<DefaultError> {
     web::scope("/healthcheck").service(healthcheck)
 }
+
+pub(crate) fn livez_routes() -> web::Scope<DefaultError> {
+    web::scope("/livez").service(healthcheck)
+}
@@ -0,0 +1,684 @@
+use crate::audit::{self, Event, Peer};
+use crate::database::{MEMBERSHIP_PROOFS, SETS};
+use crate::metrics::observe_route;
+use crate::router::handshake::new_client_id;
+use crate::set_data::{MembershipProof, SetData};
+use crate::token;
+use crate::totp;
+use crate::web_utils::{bearer_token, from_wire, stored_to_wire, to_wire, width_of, JsonErr};
+use ntex::http::{Response as HttpResponse, StatusCode};
+use ntex::web;
+use ntex::web::DefaultError;
+use openssl::bn::{BigNum, BigNumRef};
+use pwd_dl_zkp_core::encoding::{self, Width};
+use pwd_dl_zkp_core::protocol::CHALLENGE_BITS;
+use pwd_dl_zkp_victor::victor::{KnowledgeProof, OrProof, Victor};
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use serde_json::from_slice;
+use sled::transaction::{TransactionError, TransactionResult};
+use std::borrow::Borrow;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = MembershipSetResponse)]
+pub(crate) struct SetResponse {
+    #[serde(with = "encoding::hex")]
+    #[schema(value_type = String, pattern = "^([0-9a-f]{2})+$")]
+    p: Vec<u8>,
+    #[serde(with = "encoding::hex")]
+    #[schema(value_type = String, pattern = "^([0-9a-f]{2})+$")]
+    g: Vec<u8>,
+    /// The enrolled keys, proofs must commit to all of them in this order.
+    #[serde(with = "encoding::hex::seq")]
+    #[schema(value_type = Vec<String>)]
+    keys: Vec<Vec<u8>>,
+}
+
+/// The key to enroll, and a Fiat–Shamir proof of the knowledge of its discrete log made for the
+/// context `<client id>.<transcript>.<set>` of the session the token was issued for.
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = MembershipKeyRequest)]
+pub(crate) struct KeyRequest {
+    #[serde(with = "encoding::hex")]
+    #[schema(value_type = String, pattern = "^([0-9a-f]{2})+$")]
+    y: Vec<u8>,
+    #[serde(with = "encoding::hex")]
+    #[schema(value_type = String, pattern = "^([0-9a-f]{2})+$")]
+    t: Vec<u8>,
+    #[serde(with = "encoding::hex")]
+    #[schema(value_type = String, pattern = "^([0-9a-f]{2})+$")]
+    s: Vec<u8>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = MembershipCommitRequest)]
+pub(crate) struct CommitRequest {
+    /// The keys returned by `GET /membership/{set}`.
+    #[serde(with = "encoding::hex::seq")]
+    #[schema(value_type = Vec<String>)]
+    keys: Vec<Vec<u8>>,
+    /// One commitment for every key.
+    #[serde(with = "encoding::hex::seq")]
+    #[schema(value_type = Vec<String>)]
+    t: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = MembershipCommitResponse)]
+pub(crate) struct CommitResponse {
+    #[serde(rename = "proofId")]
+    proof_id: String,
+    /// `CHALLENGE_BITS` wide.
+    #[serde(with = "encoding::hex")]
+    #[schema(value_type = String, pattern = "^[0-9a-f]{32}$")]
+    challenge: Vec<u8>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = MembershipRespondRequest)]
+pub(crate) struct RespondRequest {
+    #[serde(rename = "proofId")]
+    proof_id: String,
+    /// One challenge for every key, adding up to the server's challenge.
+    #[serde(with = "encoding::hex::seq")]
+    #[schema(value_type = Vec<String>)]
+    e: Vec<Vec<u8>>,
+    /// One response for every key.
+    #[serde(with = "encoding::hex::seq")]
+    #[schema(value_type = Vec<String>)]
+    s: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = MembershipRespondResponse)]
+pub(crate) struct RespondResponse {
+    valid: bool,
+    /// Bearer token scoped to the set, only issued for a valid proof.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+}
+
+/// Proofs of a set waiting for their response at once, further commitments are refused.
+const MAX_PENDING_PROOFS: usize = 64;
+
+/// Removes the expired proofs, and counts the ones of `set` still waiting for their response.
+fn sweep_proofs(set: &str, now: u64) -> usize {
+    let mut pending = 0;
+    for (proof_id, proof) in MEMBERSHIP_PROOFS.iter().flatten() {
+        let proof: MembershipProof = match from_slice(proof.borrow()) {
+            Ok(proof) => proof,
+            Err(_) => continue,
+        };
+
+        if proof.expired(now) {
+            MEMBERSHIP_PROOFS.remove(proof_id).unwrap();
+        } else if proof.set == set && proof.valid.is_none() {
+            pending += 1;
+        }
+    }
+    pending
+}
+
+fn get_set(set: &str) -> Result<SetData, JsonErr> {
+    SETS.get(set)
+        .unwrap()
+        .map(|data| from_slice(data.borrow()).unwrap())
+        .ok_or(JsonErr::new(StatusCode::NOT_FOUND, "no such set"))
+}
+
+fn set_width(data: &SetData) -> Width {
+    width_of(&BigNum::from_hex_str(&data.p).unwrap())
+}
+
+fn challenge_width() -> Width {
+    Width(CHALLENGE_BITS / 8)
+}
+
+fn parse_numbers(numbers: &[Vec<u8>], width: Width, name: &str) -> Result<Vec<BigNum>, JsonErr> {
+    numbers
+        .iter()
+        .map(|number| from_wire(number, width, name))
+        .collect()
+}
+
+/// The enrolled keys of `data`, in the canonical encoding.
+fn wire_keys(data: &SetData, width: Width) -> Vec<Vec<u8>> {
+    data.keys()
+        .iter()
+        .map(|key| stored_to_wire(key, width))
+        .collect()
+}
+
+pub(crate) fn do_get_set(set: &str) -> Result<SetResponse, JsonErr> {
+    let data = get_set(set)?;
+    let width = set_width(&data);
+
+    Ok(SetResponse {
+        keys: wire_keys(&data, width),
+        p: stored_to_wire(&data.p, width),
+        g: stored_to_wire(&data.g, width),
+    })
+}
+
+/// Enrolls `y` for the user authenticated by `token`, once proven to be the key of a secret
+/// they know, so that nobody can enroll a key they couldn't prove membership with, or someone
+/// else's.
+pub(crate) fn do_enroll(
+    set: &str,
+    token: &str,
+    y: &[u8],
+    t: &[u8],
+    s: &[u8],
+) -> Result<(), JsonErr> {
+    let (client_id, session) =
+        token::verify(token).ok_or(JsonErr::new(StatusCode::UNAUTHORIZED, "invalid token"))?;
+    let username = session.username.ok_or(JsonErr::new(
+        StatusCode::FORBIDDEN,
+        "anonymous sessions cannot enroll",
+    ))?;
+
+    let data = get_set(set)?;
+    let width = set_width(&data);
+    let (p, g) = (
+        BigNum::from_hex_str(&data.p).unwrap(),
+        BigNum::from_hex_str(&data.g).unwrap(),
+    );
+    let new_y = from_wire(y, width, "y")?;
+    let proof = KnowledgeProof {
+        t: from_wire(t, width, "t")?,
+        s: from_wire(s, width, "s")?,
+    };
+
+    // 1 and p - 1 have trivially known logs.
+    let one = BigNum::from_u32(1).unwrap();
+    let mut p_1 = BigNum::new().unwrap();
+    p_1.checked_sub(&p, &one).unwrap();
+    if new_y <= one || new_y >= p_1 {
+        return Err(JsonErr::new(
+            StatusCode::BAD_REQUEST,
+            "y is not a valid key",
+        ));
+    }
+
+    let context = format!("{}.{}.{}", client_id, session.transcript, set);
+    let valid = Victor::new()
+        .verify_knowledge(&new_y, &g, &p, context.as_bytes(), &proof)
+        .or(Err(JsonErr::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to verify",
+        )))?;
+    if !valid {
+        return Err(JsonErr::new(
+            StatusCode::BAD_REQUEST,
+            "proof of the key not valid",
+        ));
+    }
+
+    let tx_res: TransactionResult<(), JsonErr> = SETS.transaction(|tx_sets| {
+        let mut current: SetData = match tx_sets.get(set).unwrap() {
+            Some(data) => from_slice(data.borrow()).unwrap(),
+            None => {
+                return sled::transaction::abort(JsonErr::new(StatusCode::NOT_FOUND, "no such set"))
+            }
+        };
+
+        // The proof is only good for the group it was verified in.
+        if current.p != data.p || current.g != data.g {
+            return sled::transaction::abort(JsonErr::new(
+                StatusCode::CONFLICT,
+                "the set has changed",
+            ));
+        }
+
+        match current.members.get_mut(username.as_str()) {
+            Some(key) => *key = Some(encoding::to_hex(y)),
+            None => {
+                return sled::transaction::abort(JsonErr::new(
+                    StatusCode::FORBIDDEN,
+                    "not a member of this set",
+                ))
+            }
+        }
+
+        tx_sets
+            .insert(set, serde_json::to_string(&current).unwrap().as_str())
+            .unwrap();
+
+        Ok(())
+    });
+
+    tx_res.map_err(|err| match err {
+        TransactionError::Abort(err) => err,
+        TransactionError::Storage(_) => {
+            JsonErr::new(StatusCode::INTERNAL_SERVER_ERROR, "unable to enroll")
+        }
+    })
+}
+
+pub(crate) fn do_commit(
+    set: &str,
+    keys: &[Vec<u8>],
+    t: &[Vec<u8>],
+) -> Result<CommitResponse, JsonErr> {
+    let data = get_set(set)?;
+    let width = set_width(&data);
+
+    if keys != wire_keys(&data, width).as_slice() {
+        return Err(JsonErr::new(StatusCode::CONFLICT, "the set has changed"));
+    }
+    if keys.is_empty() {
+        return Err(JsonErr::new(
+            StatusCode::CONFLICT,
+            "the set has no enrolled keys",
+        ));
+    }
+    if t.len() != keys.len() {
+        return Err(JsonErr::new(
+            StatusCode::BAD_REQUEST,
+            "t must have one commitment for every key",
+        ));
+    }
+    parse_numbers(t, width, "t")?;
+
+    let commit_err = || JsonErr::new(StatusCode::INTERNAL_SERVER_ERROR, "unable to commit");
+    let proof_id = new_client_id().or(Err(commit_err()))?;
+    let challenge = Victor::new()
+        .pick_challenge(&mut OsRng)
+        .map(|challenge| to_wire(&challenge, challenge_width()))
+        .or(Err(commit_err()))?;
+
+    let now = totp::now();
+    if sweep_proofs(set, now) >= MAX_PENDING_PROOFS {
+        return Err(JsonErr::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "too many pending proofs",
+        ));
+    }
+
+    let proof = MembershipProof {
+        set: set.to_string(),
+        keys: keys.iter().map(|key| encoding::to_hex(key)).collect(),
+        t: t.iter().map(|t| encoding::to_hex(t)).collect(),
+        challenge: encoding::to_hex(&challenge),
+        valid: None,
+        committed_at: now,
+    };
+    MEMBERSHIP_PROOFS
+        .insert(
+            proof_id.as_str(),
+            serde_json::to_string(&proof).unwrap().as_str(),
+        )
+        .unwrap();
+
+    Ok(CommitResponse {
+        proof_id,
+        challenge,
+    })
+}
+
+/// The proof `proof_id` of `set`, as long as it still waits for its response.
+fn pending_proof(
+    set: &str,
+    stored: Option<sled::IVec>,
+    now: u64,
+) -> Result<MembershipProof, JsonErr> {
+    let proof: MembershipProof = stored
+        .map(|proof| from_slice(proof.borrow()).unwrap())
+        .filter(|proof: &MembershipProof| proof.set == set && !proof.expired(now))
+        .ok_or(JsonErr::new(StatusCode::NOT_FOUND, "no such proof"))?;
+
+    if proof.valid.is_some() {
+        return Err(JsonErr::new(StatusCode::CONFLICT, "proof already answered"));
+    }
+
+    Ok(proof)
+}
+
+pub(crate) fn do_respond(
+    set: &str,
+    proof_id: &str,
+    e: &[Vec<u8>],
+    s: &[Vec<u8>],
+) -> Result<RespondResponse, JsonErr> {
+    let e = parse_numbers(e, challenge_width(), "e")?;
+
+    let now = totp::now();
+    let proof = pending_proof(set, MEMBERSHIP_PROOFS.get(proof_id).unwrap(), now)?;
+    let data = get_set(set)?;
+    let s = parse_numbers(s, set_width(&data), "s")?;
+
+    let parse_stored = |numbers: &[String]| -> Result<Vec<BigNum>, _> {
+        numbers
+            .iter()
+            .map(|number| BigNum::from_hex_str(number))
+            .collect()
+    };
+    let (ys, t, p, g, challenge) = match (
+        parse_stored(&proof.keys),
+        parse_stored(&proof.t),
+        BigNum::from_hex_str(&data.p),
+        BigNum::from_hex_str(&data.g),
+        BigNum::from_hex_str(&proof.challenge),
+    ) {
+        (Ok(ys), Ok(t), Ok(p), Ok(g), Ok(challenge)) => (ys, t, p, g, challenge),
+        _ => {
+            return Err(JsonErr::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to load proof",
+            ))
+        }
+    };
+
+    let or_proof = OrProof {
+        t,
+        e: e.iter().map(|e| BigNumRef::to_owned(e).unwrap()).collect(),
+        s: s.iter().map(|s| BigNumRef::to_owned(s).unwrap()).collect(),
+    };
+    let valid = Victor::new()
+        .verify_or(&ys, &g, &p, &challenge, &or_proof)
+        .or(Err(JsonErr::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to verify",
+        )))?;
+
+    let tx_res: TransactionResult<bool, JsonErr> = MEMBERSHIP_PROOFS.transaction(|tx_proofs| {
+        // Only the first response to be stored counts.
+        let mut current = match pending_proof(set, tx_proofs.get(proof_id).unwrap(), now) {
+            Ok(current) => current,
+            Err(err) => return sled::transaction::abort(err),
+        };
+
+        current.valid = Some(valid);
+        tx_proofs
+            .insert(proof_id, serde_json::to_string(&current).unwrap().as_str())
+            .unwrap();
+
+        Ok(valid)
+    });
+
+    let valid = tx_res.map_err(|err| match err {
+        TransactionError::Abort(err) => err,
+        TransactionError::Storage(_) => {
+            JsonErr::new(StatusCode::INTERNAL_SERVER_ERROR, "unable to verify")
+        }
+    })?;
+
+    audit::record(
+        proof_id,
+        &Peer::default(),
+        Event::MembershipProof {
+            set: set.to_string(),
+            valid,
+        },
+    );
+
+    Ok(RespondResponse {
+        valid,
+        token: valid.then(|| token::issue_membership(set, proof_id)),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/membership/{set}",
+    params(("set" = String, Path, description = "Name of the set")),
+    responses(
+        (status = 200, body = MembershipSetResponse),
+        (status = 404, body = ErrorResponse),
+    ),
+)]
+#[web::get("/{set}")]
+async fn set_keys(set: web::types::Path<String>) -> Result<HttpResponse, web::Error> {
+    observe_route("/membership/{set}", async move {
+        Ok::<_, web::Error>(HttpResponse::Ok().json(&do_get_set(set.as_str())?))
+    })
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/membership/{set}/key",
+    params(
+        ("authorization" = String, Header, description = "Bearer token returned by /authenticated"),
+        ("set" = String, Path, description = "Name of the set"),
+    ),
+    request_body = MembershipKeyRequest,
+    responses(
+        (status = 200, description = "The key has been enrolled"),
+        (status = 400, body = ErrorResponse),
+        (status = 401, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+    ),
+)]
+#[web::post("/{set}/key")]
+async fn enroll(
+    req: web::HttpRequest,
+    set: web::types::Path<String>,
+    data: web::types::Json<KeyRequest>,
+) -> Result<HttpResponse, web::Error> {
+    observe_route("/membership/{set}/key", async move {
+        let token = bearer_token(&req)?;
+
+        Ok::<_, web::Error>(HttpResponse::Ok().json(&do_enroll(
+            set.as_str(),
+            token,
+            &data.y,
+            &data.t,
+            &data.s,
+        )?))
+    })
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/membership/{set}/commit",
+    params(("set" = String, Path, description = "Name of the set")),
+    request_body = MembershipCommitRequest,
+    responses(
+        (status = 200, body = MembershipCommitResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 409, body = ErrorResponse),
+        (status = 503, body = ErrorResponse),
+    ),
+)]
+#[web::post("/{set}/commit")]
+async fn commit(
+    set: web::types::Path<String>,
+    data: web::types::Json<CommitRequest>,
+) -> Result<HttpResponse, web::Error> {
+    observe_route("/membership/{set}/commit", async move {
+        Ok::<_, web::Error>(HttpResponse::Ok().json(&do_commit(set.as_str(), &data.keys, &data.t)?))
+    })
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/membership/{set}/respond",
+    params(("set" = String, Path, description = "Name of the set")),
+    request_body = MembershipRespondRequest,
+    responses(
+        (status = 200, body = MembershipRespondResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 409, body = ErrorResponse),
+    ),
+)]
+#[web::post("/{set}/respond")]
+async fn respond(
+    set: web::types::Path<String>,
+    data: web::types::Json<RespondRequest>,
+) -> Result<HttpResponse, web::Error> {
+    observe_route("/membership/{set}/respond", async move {
+        Ok::<_, web::Error>(HttpResponse::Ok().json(&do_respond(
+            set.as_str(),
+            data.proof_id.as_str(),
+            &data.e,
+            &data.s,
+        )?))
+    })
+    .await
+}
+
+#[utoipa::path(
+    get,
+    path = "/membership/{set}/token",
+    params(
+        ("authorization" = String, Header, description = "Bearer token returned by /membership/{set}/respond"),
+        ("set" = String, Path, description = "Name of the set"),
+    ),
+    responses(
+        (status = 200, description = "The bearer is a member of the set"),
+        (status = 401, body = ErrorResponse),
+    ),
+)]
+#[web::get("/{set}/token")]
+async fn membership_token(
+    req: web::HttpRequest,
+    set: web::types::Path<String>,
+) -> Result<HttpResponse, web::Error> {
+    observe_route("/membership/{set}/token", async move {
+        if !token::verify_membership(bearer_token(&req)?, set.as_str()) {
+            return Err(JsonErr::new(StatusCode::UNAUTHORIZED, "invalid token").into());
+        }
+
+        Ok::<_, web::Error>(HttpResponse::Ok().finish())
+    })
+    .await
+}
+
+pub(crate) fn routes() -> web::Scope<DefaultError> {
+    web::scope("/membership").service((set_keys, enroll, commit, respond, membership_token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{challenge_width, MAX_PENDING_PROOFS};
+    use crate::database::{MEMBERSHIP_PROOFS, SETS};
+    use crate::set_data::{MembershipProof, SetData};
+    use crate::test_utils::{admin_post, app, login, number, prove_knowledge, register, wire};
+    use ntex::http::StatusCode;
+    use ntex::web::test::{call_service, read_response_json, TestRequest};
+    use num_bigint::BigInt;
+    use pwd_dl_zkp_peggy::peggy::Peggy;
+    use rand::thread_rng;
+    use serde_json::{from_slice, json, Value};
+
+    /// Only keys proven within the member's session are enrolled, and never ones with a
+    /// trivially known log.
+    #[ntex::test]
+    async fn enroll() {
+        let app = app().await;
+        let (session, x) = register(&app, "member").await;
+        let auth = login(&app, &session, &x).await;
+        let token = auth["token"].as_str().unwrap();
+
+        for path in ["/admin/sets/enroll", "/admin/sets/enroll/members/member"] {
+            assert_eq!(
+                call_service(&app, admin_post(path)).await.status(),
+                StatusCode::OK
+            );
+        }
+
+        let set: Value = read_response_json(
+            &app,
+            TestRequest::get().uri("/membership/enroll").to_request(),
+        )
+        .await;
+        let (p, g) = (number(&set["p"]), number(&set["g"]));
+        let width = Peggy::width(&p);
+
+        let context = format!(
+            "{}.{}.enroll",
+            session.client_id,
+            auth["transcript"].as_str().unwrap()
+        );
+        let key = Peggy::gen_r(&Peggy::order(&g, &p), &mut thread_rng()).unwrap();
+
+        for (x, context, status) in [
+            (BigInt::from(0), context.as_str(), StatusCode::BAD_REQUEST),
+            (key.clone(), "another session", StatusCode::BAD_REQUEST),
+            (key.clone(), context.as_str(), StatusCode::OK),
+        ] {
+            let y = Peggy::public_key(&x, &g, &p).unwrap();
+            let proof = prove_knowledge(&x, &g, &p, context);
+
+            let req = TestRequest::post()
+                .uri("/membership/enroll/key")
+                .header("authorization", format!("Bearer {}", token))
+                .set_json(&json!({
+                    "y": wire(&y, width),
+                    "t": wire(&proof.t, width),
+                    "s": wire(&proof.s, width),
+                }))
+                .to_request();
+            assert_eq!(call_service(&app, req).await.status(), status, "{}", x);
+        }
+    }
+
+    /// Unanswered proofs are limited per set, and expire.
+    #[ntex::test]
+    async fn pending_proofs() {
+        let app = app().await;
+        assert_eq!(
+            call_service(&app, admin_post("/admin/sets/pending"))
+                .await
+                .status(),
+            StatusCode::OK
+        );
+
+        // Enrolled directly, the commitments aren't answered anyway.
+        let mut data: SetData = from_slice(&SETS.get("pending").unwrap().unwrap()).unwrap();
+        let (p, g) = (number(&json!(data.p)), number(&json!(data.g)));
+        let y = Peggy::public_key(&BigInt::from(2), &g, &p).unwrap();
+        data.members
+            .insert("member".to_string(), Some(y.to_str_radix(16)));
+        SETS.insert("pending", serde_json::to_string(&data).unwrap().as_str())
+            .unwrap();
+
+        let y = wire(&y, Peggy::width(&p));
+        let commit = || {
+            TestRequest::post()
+                .uri("/membership/pending/commit")
+                .set_json(&json!({ "keys": [y], "t": [y] }))
+                .to_request()
+        };
+
+        let mut proof_ids = vec![];
+        for _ in 0..MAX_PENDING_PROOFS {
+            let res: Value = read_response_json(&app, commit()).await;
+            proof_ids.push(res["proofId"].as_str().unwrap().to_string());
+        }
+        assert_eq!(
+            call_service(&app, commit()).await.status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        let expired = proof_ids[0].as_str();
+        let mut proof: MembershipProof =
+            from_slice(&MEMBERSHIP_PROOFS.get(expired).unwrap().unwrap()).unwrap();
+        proof.committed_at = 0;
+        MEMBERSHIP_PROOFS
+            .insert(expired, serde_json::to_string(&proof).unwrap().as_str())
+            .unwrap();
+
+        let respond = TestRequest::post()
+            .uri("/membership/pending/respond")
+            .set_json(&json!({
+                "proofId": expired,
+                "e": ["00".repeat(challenge_width().0)],
+                "s": [y],
+            }))
+            .to_request();
+        assert_eq!(
+            call_service(&app, respond).await.status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(call_service(&app, commit()).await.status(), StatusCode::OK);
+        assert!(MEMBERSHIP_PROOFS.get(expired).unwrap().is_none());
+    }
+}
@@ -0,0 +1,75 @@
+use crate::database::DB;
+use crate::metrics::{DB_ENTRIES, DB_SIZE_ON_DISK_BYTES};
+use crate::web_utils::JsonErr;
+use ntex::http::{Response as HttpResponse, StatusCode};
+use ntex::web;
+use ntex::web::DefaultError;
+use prometheus::{Encoder, TextEncoder};
+
+fn do_metrics() -> Result<Vec<u8>, JsonErr> {
+    {
+        let db = DB.lock().unwrap();
+        DB_SIZE_ON_DISK_BYTES.set(db.size_on_disk().unwrap_or_default() as i64);
+        DB_ENTRIES.set(db.len() as i64);
+    }
+
+    let mut buffer = vec![];
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .or(Err(JsonErr::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to encode metrics",
+        )))?;
+
+    Ok(buffer)
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses((status = 200, body = String, content_type = "text/plain; version=0.0.4")),
+)]
+#[web::get("")]
+async fn metrics() -> Result<HttpResponse, web::Error> {
+    Ok(HttpResponse::Ok()
+        .content_type(TextEncoder::new().format_type())
+        .body(do_metrics()?))
+}
+
+pub(crate) fn routes() -> web::Scope<DefaultError> {
+    web::scope("/metrics").service(metrics)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{app, register, round};
+    use ntex::web::test::{read_response, TestRequest};
+    use ntex::web::WebResponse;
+    use ntex::{http::Request, Service};
+
+    async fn scrape(app: &impl Service<Request, Response = WebResponse>, name: &str) -> f64 {
+        let body = read_response(app, TestRequest::get().uri("/metrics").to_request()).await;
+
+        String::from_utf8(body.to_vec())
+            .unwrap()
+            .lines()
+            .filter(|line| line.starts_with(name))
+            .map(|line| line.rsplit(' ').next().unwrap().parse::<f64>().unwrap())
+            .sum()
+    }
+
+    /// The registry is shared by the tests running alongside, so only the increments are checked.
+    #[ntex::test]
+    async fn counts_rounds() {
+        let app = app().await;
+        let (session, x) = register(&app, "metrics").await;
+
+        let verified = scrape(&app, "zkp_verify_seconds_count").await;
+        let proofs = scrape(&app, "zkp_proofs_total").await;
+
+        assert_eq!(round(&app, &session, &x).await["valid"], true);
+
+        assert!(scrape(&app, "zkp_verify_seconds_count").await >= verified + 1.0);
+        assert!(scrape(&app, "zkp_proofs_total").await >= proofs + 1.0);
+    }
+}
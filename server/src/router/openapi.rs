@@ -0,0 +1,259 @@
+use super::healthcheck::Livez;
+use super::{
+    admin, authenticated, handshake, healthcheck, membership, metrics, password, pick_choice,
+    public_key, readyz, session, totp, verify, ws,
+};
+use crate::client_data::Factors;
+use crate::web_utils::JsonErr;
+use ntex::web;
+use ntex::web::{DefaultError, HttpResponse};
+use pwd_dl_zkp_core::core::Choice;
+use pwd_dl_zkp_core::protocol::Version;
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        description = "Password authentication through a zero-knowledge proof of the discrete logarithm. \
+        Big numbers are exchanged as hex strings, and every call after /handshake must carry \
+        the returned client id in the x-client-id header.\n\n\
+        Sessions opened with a username are bound to that user. The first one registers it: the \
+        handshake draws a new group and answers registered = false, and the key posted to \
+        /public-key is stored for the user along with the group and the protocol version. \
+        Later handshakes answer registered = true with the stored group, and /public-key \
+        refuses them with a 409, the proof is run against the stored key. Once support staff \
+        re-enrolled a user, handshakes answer registered = false again and /public-key takes \
+        the one-time code handed out along the new key. Sessions opened \
+        without a username are anonymous and register nothing."
+    ),
+    paths(
+        healthcheck::healthcheck,
+        readyz::readyz,
+        metrics::metrics,
+        handshake::handshake,
+        public_key::public_key,
+        pick_choice::pick_choice,
+        verify::verify,
+        authenticated::authenticated,
+        session::session,
+        password::password,
+        totp::enroll,
+        totp::confirm,
+        totp::verify,
+        membership::set_keys,
+        membership::enroll,
+        membership::commit,
+        membership::respond,
+        membership::membership_token,
+        admin::list_sessions,
+        admin::revoke_session,
+        admin::lock_user,
+        admin::unlock_user,
+        admin::reenroll_user,
+        admin::create_set,
+        admin::add_member,
+        admin::remove_member,
+        ws::ws_index,
+        document,
+    ),
+    components(schemas(
+        Choice,
+        Version,
+        Factors,
+        JsonErr,
+        healthcheck::Response,
+        readyz::Checks,
+        readyz::Response,
+        handshake::Response,
+        public_key::Request,
+        pick_choice::Request,
+        pick_choice::Response,
+        verify::Request,
+        verify::Response,
+        authenticated::Response,
+        session::Response,
+        password::Request,
+        totp::EnrollResponse,
+        totp::CodeRequest,
+        totp::VerifyResponse,
+        membership::SetResponse,
+        membership::KeyRequest,
+        membership::CommitRequest,
+        membership::CommitResponse,
+        membership::RespondRequest,
+        membership::RespondResponse,
+        admin::Session,
+        admin::ReenrollResponse,
+    )),
+    modifiers(&Livez)
+)]
+pub(crate) struct ApiDoc;
+
+#[utoipa::path(
+    get,
+    path = "/openapi.json",
+    responses((status = 200, description = "This document")),
+)]
+#[web::get("")]
+async fn document() -> HttpResponse {
+    HttpResponse::Ok().json(&ApiDoc::openapi())
+}
+
+pub(crate) fn routes() -> web::Scope<DefaultError> {
+    web::scope("/openapi.json").service(document)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ApiDoc;
+    use crate::router::routes;
+    use ntex::http::{Method, StatusCode};
+    use ntex::web::{test, App};
+    use utoipa::openapi::PathItemType;
+    use utoipa::OpenApi;
+
+    fn method(item_type: &PathItemType) -> Method {
+        match item_type {
+            PathItemType::Get => Method::GET,
+            PathItemType::Post => Method::POST,
+            PathItemType::Put => Method::PUT,
+            PathItemType::Delete => Method::DELETE,
+            PathItemType::Options => Method::OPTIONS,
+            PathItemType::Head => Method::HEAD,
+            PathItemType::Patch => Method::PATCH,
+            PathItemType::Trace => Method::TRACE,
+            PathItemType::Connect => Method::CONNECT,
+        }
+    }
+
+    /// The sources of the modules mounted by `routes()`.
+    const SOURCES: [&str; 16] = [
+        include_str!("admin.rs"),
+        include_str!("authenticated.rs"),
+        include_str!("handshake.rs"),
+        include_str!("healthcheck.rs"),
+        include_str!("membership.rs"),
+        include_str!("metrics.rs"),
+        include_str!("openapi.rs"),
+        include_str!("password.rs"),
+        include_str!("pick_choice.rs"),
+        include_str!("public_key.rs"),
+        include_str!("readyz.rs"),
+        include_str!("session.rs"),
+        include_str!("totp.rs"),
+        include_str!("verify.rs"),
+        include_str!("ws.rs"),
+        include_str!("../router.rs"),
+    ];
+
+    /// The string literals passed to `prefix`, or to the functions named after it.
+    fn literals<'a>(source: &'a str, prefix: &str) -> Vec<(&'a str, &'a str)> {
+        source
+            .split(prefix)
+            .skip(1)
+            .filter_map(|rest| {
+                let (name, rest) = rest.split_once("(\"")?;
+                let (literal, _) = rest.split_once('"')?;
+                name.chars()
+                    .all(|c| c.is_ascii_lowercase())
+                    .then_some((name, literal))
+            })
+            .collect()
+    }
+
+    /// Every handler mounted by `routes()` must be documented. ntex keeps no table of its routes,
+    /// so they are read from the sources: each module mounts its handlers, declared with
+    /// `#[web::<method>("<path>")]`, under the `web::scope`s it builds.
+    #[test]
+    fn routes_match_spec() {
+        let spec = ApiDoc::openapi();
+        let mut count = 0;
+
+        for source in SOURCES {
+            let scopes = literals(source, "web::scope");
+            let handlers = literals(source, "#[web::");
+
+            for (_, scope) in scopes.iter() {
+                for (method, path) in handlers.iter() {
+                    let path = format!("{}{}", scope.trim_end_matches('/'), path);
+                    let item = spec.paths.paths.get(path.as_str());
+                    let documented = item.is_some_and(|item| {
+                        item.operations.keys().any(|item_type| {
+                            self::method(item_type).as_str() == method.to_uppercase()
+                        })
+                    });
+
+                    assert!(documented, "{} {}", method, path);
+                    count += 1;
+                }
+            }
+        }
+
+        assert_eq!(
+            count,
+            spec.paths
+                .paths
+                .values()
+                .map(|item| item.operations.len())
+                .sum::<usize>()
+        );
+    }
+
+    /// Every documented operation must be routed to a handler. Handlers are called with an empty
+    /// body and placeholder path parameters, so anything but a 405, or a 404 without an error
+    /// body from a handler, means the route exists with the documented method.
+    #[ntex::test]
+    async fn spec_matches_routes() {
+        let app = test::init_service(App::new().service(routes())).await;
+
+        let res = test::call_service(
+            &app,
+            test::TestRequest::get().uri("/handshake").to_request(),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let handshake: serde_json::Value =
+            serde_json::from_slice(&test::read_body(res).await).unwrap();
+        let client_id = handshake["clientId"].as_str().unwrap().to_string();
+
+        let spec = ApiDoc::openapi();
+        assert!(!spec.paths.paths.is_empty());
+
+        for (path, item) in spec.paths.paths.iter() {
+            let uri = path
+                .split('/')
+                .map(|segment| match segment.starts_with('{') {
+                    true => "placeholder",
+                    false => segment,
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+
+            for item_type in item.operations.keys() {
+                let method = method(item_type);
+                let req = test::TestRequest::default()
+                    .method(method.clone())
+                    .uri(uri.as_str())
+                    .header("x-client-id", client_id.as_str())
+                    .header("content-type", "application/json")
+                    .to_request();
+                let res = test::call_service(&app, req).await;
+                let status = res.status();
+
+                if status == StatusCode::NOT_FOUND {
+                    let body = test::read_body(res).await;
+                    let handled = serde_json::from_slice::<serde_json::Value>(&body)
+                        .is_ok_and(|body| body["err"].is_string());
+                    assert!(handled, "{} {}", method, path);
+                }
+                assert_ne!(
+                    status,
+                    StatusCode::METHOD_NOT_ALLOWED,
+                    "{} {}",
+                    method,
+                    path
+                );
+            }
+        }
+    }
+}
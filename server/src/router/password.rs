@@ -0,0 +1,343 @@
+use crate::audit::{self, Event, Peer};
+use crate::database::{sessions_of, DB, SETS, USERS};
+use crate::metrics::observe_route;
+use crate::set_data::SetData;
+use crate::token;
+use crate::user_data::UserData;
+use crate::web_utils::{bearer_token, from_wire, width_of, JsonErr};
+use ntex::http::{Response as HttpResponse, StatusCode};
+use ntex::web;
+use ntex::web::DefaultError;
+use openssl::bn::BigNum;
+use pwd_dl_zkp_core::encoding;
+use pwd_dl_zkp_victor::victor::{KnowledgeProof, Victor};
+use serde::Deserialize;
+use serde_json::from_slice;
+use sled::transaction::{TransactionError, TransactionResult, Transactional};
+use std::borrow::Borrow;
+use utoipa::ToSchema;
+
+/// The new public key, and a Fiat–Shamir proof of the knowledge of its discrete log made for
+/// the context `<client id>.<transcript>` of the session the token was issued for. `oldT` and
+/// `oldS` prove the knowledge of the current password, for the context
+/// `<client id>.<transcript>.<new y>`.
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = PasswordRequest)]
+pub(crate) struct Request {
+    #[serde(with = "encoding::hex")]
+    #[schema(value_type = String, pattern = "^([0-9a-f]{2})+$")]
+    y: Vec<u8>,
+    #[serde(with = "encoding::hex")]
+    #[schema(value_type = String, pattern = "^([0-9a-f]{2})+$")]
+    t: Vec<u8>,
+    #[serde(with = "encoding::hex")]
+    #[schema(value_type = String, pattern = "^([0-9a-f]{2})+$")]
+    s: Vec<u8>,
+    #[serde(rename = "oldT", with = "encoding::hex")]
+    #[schema(value_type = String, pattern = "^([0-9a-f]{2})+$")]
+    old_t: Vec<u8>,
+    #[serde(rename = "oldS", with = "encoding::hex")]
+    #[schema(value_type = String, pattern = "^([0-9a-f]{2})+$")]
+    old_s: Vec<u8>,
+}
+
+/// Replaces the key of the user of the session with `y`. Holding the token isn't enough, the
+/// knowledge of the current password is proven again along with that of the new one: along with
+/// the swap, every session of the user and the keys they enrolled in sets are dropped, as they
+/// were bound to the old one.
+pub(crate) fn do_change_password(
+    token: &str,
+    y: &[u8],
+    (t, s): (&[u8], &[u8]),
+    (old_t, old_s): (&[u8], &[u8]),
+    peer: &Peer,
+) -> Result<(), JsonErr> {
+    let (client_id, session) =
+        token::verify(token).ok_or(JsonErr::new(StatusCode::UNAUTHORIZED, "invalid token"))?;
+    let username = session.username.clone().ok_or(JsonErr::new(
+        StatusCode::FORBIDDEN,
+        "anonymous sessions have no password",
+    ))?;
+
+    let (p, g) = match (&session.p, &session.g) {
+        (Some(p), Some(g)) => (
+            BigNum::from_hex_str(p).unwrap(),
+            BigNum::from_hex_str(g).unwrap(),
+        ),
+        _ => {
+            return Err(JsonErr::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to load group",
+            ))
+        }
+    };
+    let old_y = match &session.y {
+        Some(y) => BigNum::from_hex_str(y).unwrap(),
+        None => {
+            return Err(JsonErr::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to load key",
+            ))
+        }
+    };
+    let width = width_of(&p);
+    let new_y = from_wire(y, width, "y")?;
+    let proof = KnowledgeProof {
+        t: from_wire(t, width, "t")?,
+        s: from_wire(s, width, "s")?,
+    };
+    let old_proof = KnowledgeProof {
+        t: from_wire(old_t, width, "oldT")?,
+        s: from_wire(old_s, width, "oldS")?,
+    };
+
+    let victor = Victor::new();
+    let verify = |y: &BigNum, context: String, proof: &KnowledgeProof| {
+        victor
+            .verify_knowledge(y, &g, &p, context.as_bytes(), proof)
+            .or(Err(JsonErr::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to verify",
+            )))
+    };
+
+    let context = format!("{}.{}", client_id, session.transcript);
+    if !verify(
+        &old_y,
+        format!("{}.{}", context, encoding::to_hex(y)),
+        &old_proof,
+    )? {
+        return Err(JsonErr::new(
+            StatusCode::FORBIDDEN,
+            "proof of the current password not valid",
+        ));
+    }
+    if !verify(&new_y, context, &proof)? {
+        return Err(JsonErr::new(
+            StatusCode::BAD_REQUEST,
+            "proof of the new password not valid",
+        ));
+    }
+
+    // Trees can't be iterated within a transaction. Sessions opened in the meantime are bound to
+    // the old key, their tokens are refused once it is replaced.
+    let client_ids = sessions_of(username.as_str());
+    let set_names = SETS
+        .iter()
+        .keys()
+        .collect::<Result<Vec<_>, _>>()
+        .or(Err(JsonErr::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to change password",
+        )))?;
+
+    let (users, sets) = (&*USERS, &*SETS);
+    let db = DB.lock().unwrap();
+    let tx_res: TransactionResult<(), JsonErr> =
+        (&**db, users, sets).transaction(|(tx_db, tx_users, tx_sets)| {
+            let mut user: UserData = match tx_users.get(username.as_str()).unwrap() {
+                Some(user) => from_slice(user.borrow()).unwrap(),
+                None => {
+                    return sled::transaction::abort(JsonErr::new(
+                        StatusCode::NOT_FOUND,
+                        "user not registered",
+                    ))
+                }
+            };
+
+            if user.locked {
+                return sled::transaction::abort(JsonErr::new(
+                    StatusCode::FORBIDDEN,
+                    "user locked",
+                ));
+            }
+
+            // Another session may have changed it since this one was authenticated.
+            if session.y.as_deref() != Some(user.y.as_str()) {
+                return sled::transaction::abort(JsonErr::new(
+                    StatusCode::CONFLICT,
+                    "password changed in the meantime",
+                ));
+            }
+
+            user.y = encoding::to_hex(y);
+            tx_users
+                .insert(
+                    username.as_str(),
+                    serde_json::to_string(&user).unwrap().as_str(),
+                )
+                .unwrap();
+
+            for client_id in client_ids.iter() {
+                tx_db.remove(client_id).unwrap();
+            }
+
+            // The keys stay withdrawn, the membership stays.
+            for name in set_names.iter() {
+                let mut set: SetData = match tx_sets.get(name).unwrap() {
+                    Some(set) => from_slice(set.borrow()).unwrap(),
+                    None => continue,
+                };
+
+                if let Some(key) = set.members.get_mut(username.as_str()) {
+                    if key.take().is_some() {
+                        tx_sets
+                            .insert(name, serde_json::to_vec(&set).unwrap())
+                            .unwrap();
+                    }
+                }
+            }
+
+            Ok(())
+        });
+    drop(db);
+
+    tx_res.map_err(|err| match err {
+        TransactionError::Abort(err) => err,
+        TransactionError::Storage(_) => JsonErr::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to change password",
+        ),
+    })?;
+
+    audit::record(&client_id, peer, Event::PasswordChanged { username });
+
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/password",
+    params(("authorization" = String, Header, description = "Bearer token returned by /authenticated")),
+    request_body = PasswordRequest,
+    responses(
+        (status = 200, description = "The key has been replaced and the user's sessions revoked"),
+        (status = 400, body = ErrorResponse),
+        (status = 401, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 409, body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
+    ),
+)]
+#[web::post("")]
+async fn password(
+    req: web::HttpRequest,
+    data: web::types::Json<Request>,
+) -> Result<HttpResponse, web::Error> {
+    observe_route("/password", async move {
+        let token = bearer_token(&req)?;
+
+        Ok::<_, web::Error>(HttpResponse::Ok().json(&do_change_password(
+            token,
+            &data.y,
+            (&data.t, &data.s),
+            (&data.old_t, &data.old_s),
+            &Peer::from(&req),
+        )?))
+    })
+    .await
+}
+
+pub(crate) fn routes() -> web::Scope<DefaultError> {
+    web::scope("/password").service(password)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{
+        app, get, handshake, login, post, prove_knowledge, register, round, wire, Session,
+    };
+    use ntex::http::StatusCode;
+    use ntex::web::test::{call_service, read_response_json, TestRequest};
+    use num_bigint::BigInt;
+    use pwd_dl_zkp_peggy::peggy::Peggy;
+    use rand::thread_rng;
+    use serde_json::{json, Value};
+
+    /// A request replacing the key with the one of `x`, proving the current key with `old_x`.
+    fn change(session: &Session, auth: &Value, x: &BigInt, old_x: &BigInt) -> Value {
+        let (g, p, width) = (&session.g, &session.p, session.width);
+        let y = wire(&Peggy::public_key(x, g, p).unwrap(), width);
+
+        let context = format!(
+            "{}.{}",
+            session.client_id,
+            auth["transcript"].as_str().unwrap()
+        );
+        let old_context = format!("{}.{}", context, y);
+        let proof = prove_knowledge(x, g, p, &context);
+        let old_proof = prove_knowledge(old_x, g, p, &old_context);
+
+        json!({
+            "y": y,
+            "t": wire(&proof.t, width),
+            "s": wire(&proof.s, width),
+            "oldT": wire(&old_proof.t, width),
+            "oldS": wire(&old_proof.s, width),
+        })
+    }
+
+    /// A single failed round ends the session: no more rounds and no token, so no way to replace
+    /// the password.
+    #[ntex::test]
+    async fn failed_round() {
+        let app = app().await;
+        let (_, x) = register(&app, "failed-round").await;
+        let session = handshake(&app, "failed-round").await;
+
+        let res = round(&app, &session, &x).await;
+        assert_eq!(res["valid"], true);
+
+        // Rounds only revealing the commitment pass whatever the secret.
+        loop {
+            let res = round(&app, &session, &(&x + 1)).await;
+            if res["valid"] == false {
+                assert_eq!(res["cont"], false);
+                break;
+            }
+        }
+
+        let c = Peggy::calc_c(&BigInt::from(2), &session.g, &session.p).unwrap();
+        let req = post(
+            &session,
+            "/pick-choice",
+            json!({ "c": wire(&c, session.width) }),
+        );
+        assert!(!call_service(&app, req).await.status().is_success());
+
+        let auth: Value = read_response_json(&app, get(&session, "/authenticated")).await;
+        assert_eq!(auth["auth"], false);
+        assert!(auth["token"].is_null());
+    }
+
+    /// The token alone doesn't replace the password, the current one is proven again. Once
+    /// replaced, the token is revoked.
+    #[ntex::test]
+    async fn proves_current() {
+        let app = app().await;
+        let (session, x) = register(&app, "proves-current").await;
+        let auth = login(&app, &session, &x).await;
+        let token = auth["token"].as_str().unwrap();
+
+        let new_x = Peggy::gen_r(&session.q, &mut thread_rng()).unwrap();
+        for (old_x, status) in [(&new_x, StatusCode::FORBIDDEN), (&x, StatusCode::OK)] {
+            let req = TestRequest::post()
+                .uri("/password")
+                .header("authorization", format!("Bearer {}", token))
+                .set_json(&change(&session, &auth, &new_x, old_x))
+                .to_request();
+            assert_eq!(call_service(&app, req).await.status(), status);
+        }
+
+        // The session went along with the old key, and so did its token.
+        let req = TestRequest::get()
+            .uri("/session")
+            .header("authorization", format!("Bearer {}", token))
+            .to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
@@ -1,25 +1,32 @@
+use crate::audit::{self, Event, Peer};
 use crate::client_data::{ClientData, ClientTest};
 use crate::database::DB;
-use crate::web_utils::{DbTxErr, JsonErr};
+use crate::metrics::{observe_route, ROUNDS_TOTAL};
+use crate::web_utils::{client_id, from_wire, session_width, DbTxErr, JsonErr};
 use ntex::http::{Response as HttpResponse, StatusCode};
 use ntex::web;
 use ntex::web::DefaultError;
-use openssl::bn::BigNum;
 use pwd_dl_zkp_core::core::Choice;
+use pwd_dl_zkp_core::encoding;
 use pwd_dl_zkp_victor::victor::Victor;
-use rand::thread_rng;
+use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
 use serde_json::from_slice;
-use sled::transaction::TransactionResult;
+use sled::transaction::{TransactionError, TransactionResult};
 use std::borrow::Borrow;
+use utoipa::ToSchema;
 
-#[derive(Debug, Deserialize)]
-struct Request {
-    c: String,
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = PickChoiceRequest)]
+pub(crate) struct Request {
+    #[serde(with = "encoding::hex")]
+    #[schema(value_type = String, pattern = "^([0-9a-f]{2})+$")]
+    c: Vec<u8>,
 }
 
-#[derive(Debug, Serialize)]
-struct Response {
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = PickChoiceResponse)]
+pub(crate) struct Response {
     choice: Choice,
 }
 
@@ -29,14 +36,24 @@ impl Response {
     }
 }
 
-fn do_pick_choice(client_id: &str, c: &str) -> Result<Response, DbTxErr> {
-    let mut rng = thread_rng();
+pub(crate) fn do_pick_choice(client_id: &str, c: &str, peer: &Peer) -> Result<Response, DbTxErr> {
     let victor = Victor::new();
-    let choice = victor.pick_choice(&mut rng);
+    let choice = victor.pick_choice(&mut OsRng);
 
     let tx_res: TransactionResult<(), DbTxErr> = DB.lock().unwrap().transaction(|tx_db| {
-        let mut data: ClientData =
-            from_slice(tx_db.get(client_id).unwrap().unwrap().borrow()).unwrap();
+        let mut data: ClientData = match tx_db.get(client_id).unwrap() {
+            Some(data) => from_slice(data.borrow()).unwrap(),
+            None => return sled::transaction::abort(DbTxErr::new("no such session")),
+        };
+
+        // A failed round or a settled outcome ends the proof for good.
+        if data.auth.is_some() || data.failed() {
+            return sled::transaction::abort(DbTxErr::new("session already settled"));
+        }
+        // Committing again until the choice suits the commitment would pass without `x`.
+        if data.tests.last().is_some_and(|t| t.valid.is_none()) {
+            return sled::transaction::abort(DbTxErr::new("round pending"));
+        }
 
         let mut client_test = ClientTest::default();
         client_test.c = Some(String::from(c));
@@ -53,38 +70,77 @@ fn do_pick_choice(client_id: &str, c: &str) -> Result<Response, DbTxErr> {
 
         Ok(())
     });
-    tx_res.unwrap();
+    tx_res.map_err(|err| match err {
+        TransactionError::Abort(err) => err,
+        TransactionError::Storage(_) => DbTxErr::new("unable to store the round"),
+    })?;
+
+    ROUNDS_TOTAL
+        .with_label_values(&[format!("{:?}", choice).as_str()])
+        .inc();
+    audit::record(client_id, peer, Event::RoundStarted { choice });
 
     Ok(Response::new(choice))
 }
 
+#[utoipa::path(
+    post,
+    path = "/pick-choice",
+    params(("x-client-id" = String, Header, description = "Client id returned by /handshake")),
+    request_body = PickChoiceRequest,
+    responses(
+        (status = 200, body = PickChoiceResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
+    ),
+)]
 #[web::post("")]
 async fn pick_choice(
     req: web::HttpRequest,
     data: web::types::Json<Request>,
 ) -> Result<HttpResponse, web::Error> {
-    let client_id = req
-        .headers()
-        .get("x-client-id")
-        .unwrap()
-        .to_str()
-        .or(Err(JsonErr::new(
-            StatusCode::BAD_REQUEST,
-            "x-client-id header not present or not valid",
-        )))?;
-
-    BigNum::from_hex_str(&data.c).or(Err(JsonErr::new(
-        StatusCode::BAD_REQUEST,
-        "c is not a number",
-    )))?;
-
-    Ok(
-        HttpResponse::Ok().json(&do_pick_choice(client_id, data.c.as_str()).or(Err(
-            JsonErr::new(StatusCode::INTERNAL_SERVER_ERROR, "unable to pick a choice"),
-        ))?),
-    )
+    observe_route("/pick-choice", async move {
+        let client_id = client_id(&req)?;
+
+        from_wire(&data.c, session_width(client_id)?, "c")?;
+
+        Ok::<_, web::Error>(HttpResponse::Ok().json(
+            &do_pick_choice(client_id, &encoding::to_hex(&data.c), &Peer::from(&req)).or(Err(
+                JsonErr::new(StatusCode::INTERNAL_SERVER_ERROR, "unable to pick a choice"),
+            ))?,
+        ))
+    })
+    .await
 }
 
 pub(crate) fn routes() -> web::Scope<DefaultError> {
     web::scope("/pick-choice").service(pick_choice)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{app, post, register, wire};
+    use ntex::web::test::call_service;
+    use num_bigint::BigInt;
+    use pwd_dl_zkp_peggy::peggy::Peggy;
+    use serde_json::json;
+
+    /// A round left unanswered can't be replaced by a new commitment.
+    #[ntex::test]
+    async fn pending_round() {
+        let app = app().await;
+        let (session, _) = register(&app, "pending-round").await;
+
+        let c = Peggy::calc_c(&BigInt::from(2), &session.g, &session.p).unwrap();
+        let commit = || {
+            post(
+                &session,
+                "/pick-choice",
+                json!({ "c": wire(&c, session.width) }),
+            )
+        };
+
+        assert!(call_service(&app, commit()).await.status().is_success());
+        assert!(!call_service(&app, commit()).await.status().is_success());
+    }
+}
@@ -1,69 +1,161 @@
+use crate::audit::{self, Event, Peer};
 use crate::client_data::ClientData;
-use crate::database::DB;
-use crate::web_utils::{DbTxErr, JsonErr};
+use crate::database::{DB, USERS};
+use crate::metrics::observe_route;
+use crate::transcript;
+use crate::user_data::UserData;
+use crate::web_utils::{client_id, from_wire, session_width, width_of, JsonErr};
 use ntex::http::{Response as HttpResponse, StatusCode};
 use ntex::web;
 use ntex::web::DefaultError;
 use openssl::bn::BigNum;
+use pwd_dl_zkp_core::encoding;
 use serde::Deserialize;
 use serde_json::from_slice;
-use sled::transaction::TransactionResult;
+use sled::transaction::{TransactionError, TransactionResult, Transactional};
 use std::borrow::Borrow;
+use utoipa::ToSchema;
 
-#[derive(Debug, Deserialize)]
-struct Request {
-    y: String,
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = PublicKeyRequest)]
+pub(crate) struct Request {
+    #[serde(with = "encoding::hex")]
+    #[schema(value_type = String, pattern = "^([0-9a-f]{2})+$")]
+    y: Vec<u8>,
+    /// The one-time code support staff handed out, to replace the key of a re-enrolled user.
+    #[serde(default)]
+    enrollment: Option<String>,
 }
 
-fn do_public_key(client_id: &str, y: &str) -> Result<(), DbTxErr> {
-    let tx_res: TransactionResult<(), DbTxErr> = DB.lock().unwrap().transaction(|tx_db| {
-        let mut data: ClientData =
-            from_slice(tx_db.get(client_id).unwrap().unwrap().borrow()).unwrap();
+pub(crate) fn do_public_key(
+    client_id: &str,
+    y: &str,
+    enrollment: Option<&str>,
+    peer: &Peer,
+) -> Result<(), JsonErr> {
+    let users = &*USERS;
+    let db = DB.lock().unwrap();
+    let tx_res: TransactionResult<Option<String>, JsonErr> =
+        (&**db, users).transaction(|(tx_db, tx_users)| {
+            let mut data: ClientData = match tx_db.get(client_id).unwrap() {
+                Some(data) => from_slice(data.borrow()).unwrap(),
+                None => {
+                    return sled::transaction::abort(JsonErr::new(
+                        StatusCode::NOT_FOUND,
+                        "no such session",
+                    ))
+                }
+            };
 
-        data.y = Some(String::from(y));
+            if data.y.is_some() {
+                return sled::transaction::abort(JsonErr::new(
+                    StatusCode::CONFLICT,
+                    "public key already set",
+                ));
+            }
 
-        tx_db
-            .insert(
-                client_id.clone(),
-                serde_json::to_string(&data).unwrap().as_str(),
-            )
-            .unwrap();
+            let width = width_of(&BigNum::from_hex_str(data.p.as_deref().unwrap()).unwrap());
 
-        Ok(())
-    });
-    tx_res.unwrap();
+            data.y = Some(String::from(y));
+            data.transcript =
+                transcript::public_key(&data.transcript, width, &BigNum::from_hex_str(y).unwrap());
+
+            if let ClientData {
+                username: Some(username),
+                p: Some(p),
+                g: Some(g),
+                ..
+            } = &data
+            {
+                let user: Option<UserData> = tx_users
+                    .get(username.as_str())
+                    .unwrap()
+                    .map(|user| from_slice(user.borrow()).unwrap());
+
+                match (&user, enrollment) {
+                    (None, _) => {}
+                    (Some(user), Some(code)) if user.enrollment_matches(code) => {}
+                    (Some(user), _) if user.enrollment.is_some() => {
+                        return sled::transaction::abort(JsonErr::new(
+                            StatusCode::FORBIDDEN,
+                            "invalid enrollment code",
+                        ))
+                    }
+                    (Some(_), _) => {
+                        return sled::transaction::abort(JsonErr::new(
+                            StatusCode::CONFLICT,
+                            "user already registered",
+                        ))
+                    }
+                }
+
+                // Re-enrolling replaces the key and consumes the code, the second factor stays.
+                let mut new_user = UserData::new(data.version, p, g, y);
+                new_user.totp = user.and_then(|user| user.totp);
+
+                tx_users
+                    .insert(
+                        username.as_str(),
+                        serde_json::to_string(&new_user).unwrap().as_str(),
+                    )
+                    .unwrap();
+            }
+
+            tx_db
+                .insert(
+                    client_id.clone(),
+                    serde_json::to_string(&data).unwrap().as_str(),
+                )
+                .unwrap();
+
+            Ok(data.username.clone())
+        });
+    drop(db);
+
+    let username = tx_res.map_err(|err| match err {
+        TransactionError::Abort(err) => err,
+        TransactionError::Storage(_) => JsonErr::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unable to process public key",
+        ),
+    })?;
+
+    audit::record(client_id, peer, Event::KeyRegistered { username });
 
     Ok(())
 }
 
+#[utoipa::path(
+    post,
+    path = "/public-key",
+    params(("x-client-id" = String, Header, description = "Client id returned by /handshake")),
+    request_body = PublicKeyRequest,
+    responses(
+        (status = 200, description = "The public key has been stored, and registered for the user of the session"),
+        (status = 400, body = ErrorResponse),
+        (status = 403, description = "The user is being re-enrolled and the code is not theirs", body = ErrorResponse),
+        (status = 409, description = "The session already has a key, or its user is registered", body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
+    ),
+)]
 #[web::post("")]
 async fn public_key(
     req: web::HttpRequest,
     data: web::types::Json<Request>,
 ) -> Result<HttpResponse, web::Error> {
-    let client_id = req
-        .headers()
-        .get("x-client-id")
-        .unwrap()
-        .to_str()
-        .or(Err(JsonErr::new(
-            StatusCode::BAD_REQUEST,
-            "x-client-id header not present or not valid",
-        )))?;
-
-    BigNum::from_hex_str(&data.y).or(Err(JsonErr::new(
-        StatusCode::BAD_REQUEST,
-        "y is not a number",
-    )))?;
-
-    Ok(
-        HttpResponse::Ok().json(&do_public_key(client_id, data.y.as_str()).or(Err(
-            JsonErr::new(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "unable to process public key",
-            ),
-        ))?),
-    )
+    observe_route("/public-key", async move {
+        let client_id = client_id(&req)?;
+
+        from_wire(&data.y, session_width(client_id)?, "y")?;
+
+        Ok::<_, web::Error>(HttpResponse::Ok().json(&do_public_key(
+            client_id,
+            &encoding::to_hex(&data.y),
+            data.enrollment.as_deref(),
+            &Peer::from(&req),
+        )?))
+    })
+    .await
 }
 
 pub(crate) fn routes() -> web::Scope<DefaultError> {
@@ -0,0 +1,104 @@
+use crate::config::CONFIG;
+use crate::database::DB;
+use ntex::http::StatusCode;
+use ntex::web;
+use ntex::web::{DefaultError, HttpResponse};
+use openssl::bn::BigNum;
+use openssl::error::ErrorStack;
+use pwd_dl_zkp_core::core::Choice;
+use pwd_dl_zkp_core::protocol::Version;
+use pwd_dl_zkp_victor::victor::Victor;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+const PROBE_KEY: &[u8] = b"probe";
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = ReadyzChecks)]
+pub(crate) struct Checks {
+    storage: bool,
+    crypto: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = ReadyzResponse)]
+pub(crate) struct Response {
+    ok: bool,
+    checks: Checks,
+    version: &'static str,
+    config: String,
+}
+
+impl Response {
+    pub fn new(checks: Checks) -> Self {
+        Self {
+            ok: checks.storage && checks.crypto,
+            checks,
+            version: env!("CARGO_PKG_VERSION"),
+            config: CONFIG.fingerprint(),
+        }
+    }
+}
+
+fn probe_storage() -> Result<bool, sled::Error> {
+    let db = match DB.lock() {
+        Ok(db) => db,
+        Err(_) => return Ok(false),
+    };
+    let tree = db.open_tree("readyz")?;
+
+    let value = format!("{:?}", std::time::SystemTime::now());
+    tree.insert(PROBE_KEY, value.as_bytes())?;
+    tree.flush()?;
+    let read = tree.get(PROBE_KEY)?;
+    tree.remove(PROBE_KEY)?;
+
+    Ok(read.as_deref() == Some(value.as_bytes()))
+}
+
+/// Runs both kinds of round against a tiny known group (p = 23, g = 5, x = 6, r = 3), plus one
+/// response that must be rejected.
+fn probe_crypto() -> Result<bool, ErrorStack> {
+    let victor = Victor::new();
+
+    let p = BigNum::from_u32(23)?;
+    let g = BigNum::from_u32(5)?;
+    let y = BigNum::from_u32(8)?;
+    let c = BigNum::from_u32(10)?;
+
+    let r = BigNum::from_u32(3)?;
+    let xrmp = BigNum::from_u32(9)?;
+    let wrong = BigNum::from_u32(4)?;
+
+    Ok(victor.verify(Version::V2, &Choice::R, &r, &c, &y, &g, &p)?
+        && victor.verify(Version::V2, &Choice::XRMP, &xrmp, &c, &y, &g, &p)?
+        && !victor.verify(Version::V2, &Choice::R, &wrong, &c, &y, &g, &p)?)
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    responses(
+        (status = 200, body = ReadyzResponse),
+        (status = 503, body = ReadyzResponse),
+    ),
+)]
+#[web::get("")]
+async fn readyz() -> HttpResponse {
+    let res = Response::new(Checks {
+        storage: probe_storage().unwrap_or(false),
+        crypto: probe_crypto().unwrap_or(false),
+    });
+
+    let status = if res.ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    HttpResponse::Ok().status(status).json(&res)
+}
+
+pub(crate) fn routes() -> web::Scope<DefaultError> {
+    web::scope("/readyz").service(readyz)
+}
@@ -0,0 +1,55 @@
+use crate::metrics::observe_route;
+use crate::token;
+use crate::web_utils::{bearer_token, JsonErr};
+use ntex::http::{Response as HttpResponse, StatusCode};
+use ntex::web;
+use ntex::web::DefaultError;
+use pwd_dl_zkp_core::protocol::Version;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = SessionResponse)]
+pub(crate) struct Response {
+    #[serde(rename = "clientId")]
+    client_id: String,
+    username: Option<String>,
+    version: Version,
+    #[schema(pattern = "^[0-9a-f]+$")]
+    transcript: String,
+}
+
+pub(crate) fn do_session(token: &str) -> Result<Response, JsonErr> {
+    let (client_id, data) =
+        token::verify(token).ok_or(JsonErr::new(StatusCode::UNAUTHORIZED, "invalid token"))?;
+
+    Ok(Response {
+        client_id,
+        username: data.username,
+        version: data.version,
+        transcript: data.transcript,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/session",
+    params(("authorization" = String, Header, description = "Bearer token returned by /authenticated")),
+    responses(
+        (status = 200, body = SessionResponse),
+        (status = 401, body = ErrorResponse),
+    ),
+)]
+#[web::get("")]
+async fn session(req: web::HttpRequest) -> Result<HttpResponse, web::Error> {
+    observe_route("/session", async move {
+        let token = bearer_token(&req)?;
+
+        Ok::<_, web::Error>(HttpResponse::Ok().json(&do_session(token)?))
+    })
+    .await
+}
+
+pub(crate) fn routes() -> web::Scope<DefaultError> {
+    web::scope("/session").service(session)
+}
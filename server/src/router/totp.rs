@@ -0,0 +1,407 @@
+use crate::audit::{self, Event, Peer};
+use crate::client_data::ClientData;
+use crate::database::{DB, USERS};
+use crate::metrics::observe_route;
+use crate::token;
+use crate::totp;
+use crate::user_data::{Totp, UserData};
+use crate::web_utils::{bearer_token, client_id, JsonErr};
+use ntex::http::{Response as HttpResponse, StatusCode};
+use ntex::web;
+use ntex::web::DefaultError;
+use serde::{Deserialize, Serialize};
+use serde_json::from_slice;
+use sled::transaction::{TransactionError, TransactionResult, Transactional};
+use std::borrow::Borrow;
+use utoipa::ToSchema;
+
+/// Wrong codes a user can send at login before an admin has to unlock them.
+pub(crate) const MAX_ATTEMPTS: u32 = 5;
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = TotpEnrollResponse)]
+pub(crate) struct EnrollResponse {
+    /// Base32 encoded secret, for authenticator apps that can't read `uri`.
+    secret: String,
+    /// `otpauth://` URI, usually shown as a QR code.
+    uri: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = TotpCodeRequest)]
+pub(crate) struct CodeRequest {
+    #[schema(pattern = "^[0-9]{6}$")]
+    code: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = TotpVerifyResponse)]
+pub(crate) struct VerifyResponse {
+    valid: bool,
+}
+
+/// The client id and the username of the session of a token.
+fn user_of(token: &str) -> Result<(String, String), JsonErr> {
+    let (client_id, session) =
+        token::verify(token).ok_or(JsonErr::new(StatusCode::UNAUTHORIZED, "invalid token"))?;
+    let username = session.username.ok_or(JsonErr::new(
+        StatusCode::FORBIDDEN,
+        "anonymous sessions have no second factor",
+    ))?;
+
+    Ok((client_id, username))
+}
+
+fn tx_err(err: TransactionError<JsonErr>) -> JsonErr {
+    match err {
+        TransactionError::Abort(err) => err,
+        TransactionError::Storage(_) => {
+            JsonErr::new(StatusCode::INTERNAL_SERVER_ERROR, "unable to process code")
+        }
+    }
+}
+
+/// Draws a new secret for the user of the session. It replaces any previous one that was never
+/// confirmed, and is only asked for at login once `/totp/confirm` accepted a code.
+pub(crate) fn do_enroll(token: &str) -> Result<EnrollResponse, JsonErr> {
+    let (_, username) = user_of(token)?;
+    let secret = totp::generate_secret();
+
+    let tx_res: TransactionResult<(), JsonErr> = USERS.transaction(|tx_users| {
+        let mut user: UserData = match tx_users.get(username.as_str()).unwrap() {
+            Some(user) => from_slice(user.borrow()).unwrap(),
+            None => {
+                return sled::transaction::abort(JsonErr::new(
+                    StatusCode::NOT_FOUND,
+                    "user not registered",
+                ))
+            }
+        };
+
+        if user.totp_required() {
+            return sled::transaction::abort(JsonErr::new(
+                StatusCode::CONFLICT,
+                "totp already enabled",
+            ));
+        }
+
+        user.totp = Some(Totp {
+            secret: secret.clone(),
+            confirmed: false,
+            last_step: None,
+            failed_attempts: 0,
+        });
+        tx_users
+            .insert(
+                username.as_str(),
+                serde_json::to_string(&user).unwrap().as_str(),
+            )
+            .unwrap();
+
+        Ok(())
+    });
+    tx_res.map_err(tx_err)?;
+
+    Ok(EnrollResponse {
+        uri: totp::uri(&username, &secret),
+        secret,
+    })
+}
+
+/// Enables the secret drawn by `/totp` once the user shows a code generated from it.
+pub(crate) fn do_confirm(token: &str, code: &str, peer: &Peer) -> Result<(), JsonErr> {
+    let (client_id, username) = user_of(token)?;
+
+    let tx_res: TransactionResult<(), JsonErr> = USERS.transaction(|tx_users| {
+        let mut user: UserData = match tx_users.get(username.as_str()).unwrap() {
+            Some(user) => from_slice(user.borrow()).unwrap(),
+            None => {
+                return sled::transaction::abort(JsonErr::new(
+                    StatusCode::NOT_FOUND,
+                    "user not registered",
+                ))
+            }
+        };
+
+        let enrollment = match user.totp.as_mut() {
+            Some(enrollment) if !enrollment.confirmed => enrollment,
+            _ => {
+                return sled::transaction::abort(JsonErr::new(
+                    StatusCode::CONFLICT,
+                    "no totp enrollment to confirm",
+                ))
+            }
+        };
+
+        match totp::verify(&enrollment.secret, code, totp::now(), None) {
+            Some(step) => {
+                enrollment.confirmed = true;
+                enrollment.last_step = Some(step);
+            }
+            None => {
+                return sled::transaction::abort(JsonErr::new(
+                    StatusCode::BAD_REQUEST,
+                    "code not valid",
+                ))
+            }
+        }
+
+        tx_users
+            .insert(
+                username.as_str(),
+                serde_json::to_string(&user).unwrap().as_str(),
+            )
+            .unwrap();
+
+        Ok(())
+    });
+    tx_res.map_err(tx_err)?;
+
+    audit::record(&client_id, peer, Event::TotpEnabled { username });
+
+    Ok(())
+}
+
+/// Satisfies the TOTP factor of a login session, to be called once the password is proven and
+/// before `/authenticated`: codes are no oracle for whoever opens sessions without it.
+pub(crate) fn do_verify(
+    client_id: &str,
+    code: &str,
+    peer: &Peer,
+) -> Result<VerifyResponse, JsonErr> {
+    let users = &*USERS;
+    let db = DB.lock().unwrap();
+    let tx_res: TransactionResult<bool, JsonErr> =
+        (&**db, users).transaction(|(tx_db, tx_users)| {
+            let mut data: ClientData = match tx_db.get(client_id).unwrap() {
+                Some(data) => from_slice(data.borrow()).unwrap(),
+                None => {
+                    return sled::transaction::abort(JsonErr::new(
+                        StatusCode::NOT_FOUND,
+                        "no such session",
+                    ))
+                }
+            };
+
+            if !data.totp_required || data.factors.totp || data.auth.is_some() {
+                return sled::transaction::abort(JsonErr::new(
+                    StatusCode::CONFLICT,
+                    "no code expected",
+                ));
+            }
+            if !data.proven() {
+                return sled::transaction::abort(JsonErr::new(
+                    StatusCode::FORBIDDEN,
+                    "password not proven",
+                ));
+            }
+
+            let username = data.username.clone().unwrap_or_default();
+            let mut user: UserData = match tx_users.get(username.as_str()).unwrap() {
+                Some(user) => from_slice(user.borrow()).unwrap(),
+                None => {
+                    return sled::transaction::abort(JsonErr::new(
+                        StatusCode::NOT_FOUND,
+                        "user not registered",
+                    ))
+                }
+            };
+            let enrollment = match user.totp.as_mut() {
+                Some(enrollment) => enrollment,
+                None => {
+                    return sled::transaction::abort(JsonErr::new(
+                        StatusCode::CONFLICT,
+                        "totp not enabled",
+                    ))
+                }
+            };
+            if enrollment.failed_attempts >= MAX_ATTEMPTS {
+                return sled::transaction::abort(JsonErr::new(
+                    StatusCode::FORBIDDEN,
+                    "too many attempts",
+                ));
+            }
+
+            let step = totp::verify(&enrollment.secret, code, totp::now(), enrollment.last_step);
+            let valid = match step {
+                Some(step) => {
+                    enrollment.last_step = Some(step);
+                    enrollment.failed_attempts = 0;
+
+                    true
+                }
+                None => {
+                    enrollment.failed_attempts += 1;
+
+                    false
+                }
+            };
+            tx_users
+                .insert(
+                    username.as_str(),
+                    serde_json::to_string(&user).unwrap().as_str(),
+                )
+                .unwrap();
+
+            data.factors.totp = valid;
+            tx_db
+                .insert(client_id, serde_json::to_string(&data).unwrap().as_str())
+                .unwrap();
+
+            Ok(valid)
+        });
+    drop(db);
+
+    let valid = tx_res.map_err(tx_err)?;
+
+    audit::record(client_id, peer, Event::Totp { valid });
+
+    Ok(VerifyResponse { valid })
+}
+
+#[utoipa::path(
+    post,
+    path = "/totp",
+    params(("authorization" = String, Header, description = "Bearer token returned by /authenticated")),
+    responses(
+        (status = 200, body = TotpEnrollResponse),
+        (status = 401, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+        (status = 409, body = ErrorResponse),
+    ),
+)]
+#[web::post("")]
+async fn enroll(req: web::HttpRequest) -> Result<HttpResponse, web::Error> {
+    observe_route("/totp", async move {
+        let token = bearer_token(&req)?;
+
+        Ok::<_, web::Error>(HttpResponse::Ok().json(&do_enroll(token)?))
+    })
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/totp/confirm",
+    params(("authorization" = String, Header, description = "Bearer token returned by /authenticated")),
+    request_body = TotpCodeRequest,
+    responses(
+        (status = 200, description = "TOTP is required from the next login on"),
+        (status = 400, body = ErrorResponse),
+        (status = 401, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+        (status = 409, body = ErrorResponse),
+    ),
+)]
+#[web::post("/confirm")]
+async fn confirm(
+    req: web::HttpRequest,
+    data: web::types::Json<CodeRequest>,
+) -> Result<HttpResponse, web::Error> {
+    observe_route("/totp/confirm", async move {
+        let token = bearer_token(&req)?;
+
+        Ok::<_, web::Error>(HttpResponse::Ok().json(&do_confirm(
+            token,
+            data.code.as_str(),
+            &Peer::from(&req),
+        )?))
+    })
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/totp/verify",
+    params(("x-client-id" = String, Header, description = "Client id returned by /handshake")),
+    request_body = TotpCodeRequest,
+    responses(
+        (status = 200, body = TotpVerifyResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 409, body = ErrorResponse),
+    ),
+)]
+#[web::post("/verify")]
+async fn verify(
+    req: web::HttpRequest,
+    data: web::types::Json<CodeRequest>,
+) -> Result<HttpResponse, web::Error> {
+    observe_route("/totp/verify", async move {
+        let client_id = client_id(&req)?;
+
+        Ok::<_, web::Error>(HttpResponse::Ok().json(&do_verify(
+            client_id,
+            data.code.as_str(),
+            &Peer::from(&req),
+        )?))
+    })
+    .await
+}
+
+pub(crate) fn routes() -> web::Scope<DefaultError> {
+    web::scope("/totp")
+        .service(enroll)
+        .service(confirm)
+        .service(verify)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MAX_ATTEMPTS;
+    use crate::database::USERS;
+    use crate::test_utils::{admin_post, app, handshake, post, register, round, Session};
+    use crate::totp;
+    use crate::user_data::{Totp, UserData};
+    use ntex::http::StatusCode;
+    use ntex::web::test::{call_service, read_response_json};
+    use serde_json::{from_slice, json, Value};
+
+    /// Codes are only checked once the password is proven, and wrong ones add up across the
+    /// sessions of the user until an admin unlocks them.
+    #[ntex::test]
+    async fn attempts() {
+        let app = app().await;
+        let (_, x) = register(&app, "totp-attempts").await;
+
+        let mut user: UserData = from_slice(&USERS.get("totp-attempts").unwrap().unwrap()).unwrap();
+        user.totp = Some(Totp {
+            secret: totp::generate_secret(),
+            confirmed: true,
+            last_step: None,
+            failed_attempts: 0,
+        });
+        USERS
+            .insert("totp-attempts", serde_json::to_vec(&user).unwrap())
+            .unwrap();
+
+        // Never a code of any secret.
+        let wrong = |session: &Session| post(session, "/totp/verify", json!({ "code": "-" }));
+        let proven = || async {
+            let session = handshake(&app, "totp-attempts").await;
+            while round(&app, &session, &x).await["cont"] == true {}
+
+            session
+        };
+
+        let session = handshake(&app, "totp-attempts").await;
+        let res = call_service(&app, wrong(&session)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let session = proven().await;
+        for _ in 0..MAX_ATTEMPTS {
+            let res: Value = read_response_json(&app, wrong(&session)).await;
+            assert_eq!(res["valid"], false);
+        }
+
+        let session = proven().await;
+        let res = call_service(&app, wrong(&session)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = call_service(&app, admin_post("/admin/users/totp-attempts/unlock")).await;
+        assert!(res.status().is_success());
+        let res: Value = read_response_json(&app, wrong(&session)).await;
+        assert_eq!(res["valid"], false);
+    }
+}
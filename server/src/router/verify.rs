@@ -1,22 +1,34 @@
+use crate::audit::{self, Event, Peer};
 use crate::client_data::{ClientData, ClientTest};
 use crate::database::DB;
-use crate::web_utils::{DbTxErr, JsonErr};
+use crate::metrics::{observe_route, PROOFS_TOTAL, VERIFY_SECONDS};
+use crate::transcript;
+use crate::web_utils::{client_id, from_wire, session_width, width_of, DbTxErr, JsonErr};
 use ntex::http::{Response as HttpResponse, StatusCode};
 use ntex::web;
 use ntex::web::DefaultError;
 use openssl::bn::BigNum;
+use pwd_dl_zkp_core::core::Choice;
+use pwd_dl_zkp_core::encoding;
 use pwd_dl_zkp_victor::victor::Victor;
 use serde::{Deserialize, Serialize};
 use serde_json::from_slice;
-use sled::transaction::TransactionResult;
+use sled::transaction::{TransactionError, TransactionResult};
 use std::borrow::Borrow;
-
-#[derive(Debug, Deserialize)]
-struct Request {
-    res: String,
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = VerifyRequest)]
+pub(crate) struct Request {
+    #[serde(with = "encoding::hex")]
+    #[schema(value_type = String, pattern = "^([0-9a-f]{2})+$")]
+    res: Vec<u8>,
 }
 
-#[derive(Debug, Serialize)]
+/// `cont` turns false once enough rounds passed, or as soon as one fails: the session then can't
+/// be authenticated.
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = VerifyResponse)]
 pub(crate) struct Response {
     cont: bool,
     valid: bool,
@@ -28,88 +40,122 @@ impl Response {
     }
 }
 
-fn do_verify(client_id: &str, res: &BigNum) -> Result<Response, DbTxErr> {
+/// The pending round of a session and the numbers it is checked against: (choice, c, y, g, p).
+fn pending_round(data: &ClientData) -> Result<(Choice, BigNum, BigNum, BigNum, BigNum), DbTxErr> {
+    let (choice, c, y, g, p) = match (data, data.tests.last()) {
+        (
+            ClientData {
+                g: Some(g),
+                p: Some(p),
+                y: Some(y),
+                ..
+            },
+            Some(ClientTest {
+                c: Some(c),
+                choice: Some(choice),
+                valid: None,
+            }),
+        ) => (*choice, c, y, g, p),
+        (_, None) => return Err(DbTxErr::new("no round to verify")),
+        _ => return Err(DbTxErr::new("error cloning client data")),
+    };
+
+    match (
+        BigNum::from_hex_str(c.as_str()),
+        BigNum::from_hex_str(y.as_str()),
+        BigNum::from_hex_str(g.as_str()),
+        BigNum::from_hex_str(p.as_str()),
+    ) {
+        (Ok(c), Ok(y), Ok(g), Ok(p)) => Ok((choice, c, y, g, p)),
+        _ => Err(DbTxErr::new("error extracting client data")),
+    }
+}
+
+pub(crate) fn do_verify(client_id: &str, res: &BigNum, peer: &Peer) -> Result<Response, DbTxErr> {
     let victor = Victor::new();
 
-    let tx_res: TransactionResult<(bool, bool), DbTxErr> =
-        DB.lock().unwrap().transaction(|tx_db| {
-            let mut data: ClientData =
-                from_slice(tx_db.get(client_id).unwrap().unwrap().borrow()).unwrap();
-
-            let mut client_test = data.tests.pop().unwrap();
-
-            let (choice, c, y, g, p) = match (data.clone(), client_test.clone()) {
-                (
-                    ClientData {
-                        g: Some(g),
-                        p: Some(p),
-                        y: Some(y),
-                        ..
-                    },
-                    ClientTest {
-                        c: Some(c),
-                        choice: Some(choice),
-                        valid: None,
-                        ..
-                    },
-                ) => Ok((choice, c, y, g, p)),
-                _ => sled::transaction::abort(DbTxErr::new("error cloning client data")),
-            }?;
-
-            let (c, y, g, p) = match (
-                BigNum::from_hex_str(c.as_str()),
-                BigNum::from_hex_str(y.as_str()),
-                BigNum::from_hex_str(g.as_str()),
-                BigNum::from_hex_str(p.as_str()),
-            ) {
-                (Ok(c), Ok(y), Ok(g), Ok(p)) => Ok((c, y, g, p)),
-                _ => sled::transaction::abort(DbTxErr::new("error extracting client data")),
-            }?;
-
-            let valid = victor.verify(&choice, res, &c, &y, &g, &p).unwrap();
-
-            client_test.valid = Some(valid);
-            data.tests.push(client_test.clone());
-
-            tx_db
-                .insert(
-                    client_id.clone(),
-                    serde_json::to_string(&data).unwrap().as_str(),
-                )
-                .unwrap();
-
-            Ok((data.should_continue(), valid))
-        });
-
-    Ok(Response::new(tx_res.unwrap()))
+    // Checked before the transaction, sled runs its closure again on conflicts.
+    let data: ClientData = match DB.lock().unwrap().get(client_id).unwrap() {
+        Some(data) => from_slice(data.borrow()).unwrap(),
+        None => return Err(DbTxErr::new("no such session")),
+    };
+    let (choice, c, y, g, p) = pending_round(&data)?;
+
+    let timer = VERIFY_SECONDS.start_timer();
+    let valid = victor
+        .verify(data.version, &choice, res, &c, &y, &g, &p)
+        .or(Err(DbTxErr::new("unable to verify")))?;
+    timer.observe_duration();
+
+    let tx_res: TransactionResult<bool, DbTxErr> = DB.lock().unwrap().transaction(|tx_db| {
+        let mut data: ClientData = match tx_db.get(client_id).unwrap() {
+            Some(data) => from_slice(data.borrow()).unwrap(),
+            None => return sled::transaction::abort(DbTxErr::new("no such session")),
+        };
+
+        // Another response may have been verified in the meantime.
+        match pending_round(&data) {
+            Ok((_, pending_c, ..)) if pending_c == c => {}
+            _ => return sled::transaction::abort(DbTxErr::new("round already verified")),
+        }
+
+        let mut client_test = data.tests.pop().unwrap();
+        client_test.valid = Some(valid);
+        data.transcript =
+            transcript::round(&data.transcript, width_of(&p), &c, &choice, res, valid);
+        data.tests.push(client_test);
+
+        tx_db
+            .insert(
+                client_id.clone(),
+                serde_json::to_string(&data).unwrap().as_str(),
+            )
+            .unwrap();
+
+        Ok(data.should_continue())
+    });
+    let cont = tx_res.map_err(|err| match err {
+        TransactionError::Abort(err) => err,
+        TransactionError::Storage(_) => DbTxErr::new("unable to store the round"),
+    })?;
+
+    PROOFS_TOTAL
+        .with_label_values(&[if valid { "valid" } else { "invalid" }])
+        .inc();
+    audit::record(client_id, peer, Event::Round { choice, valid });
+
+    Ok(Response::new((cont, valid)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/verify",
+    params(("x-client-id" = String, Header, description = "Client id returned by /handshake")),
+    request_body = VerifyRequest,
+    responses(
+        (status = 200, body = VerifyResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
+    ),
+)]
 #[web::post("")]
 async fn verify(
     req: web::HttpRequest,
     data: web::types::Json<Request>,
 ) -> Result<HttpResponse, web::Error> {
-    let client_id = req
-        .headers()
-        .get("x-client-id")
-        .unwrap()
-        .to_str()
-        .or(Err(JsonErr::new(
-            StatusCode::BAD_REQUEST,
-            "x-client-id header not present or not valid",
-        )))?;
-
-    let res = BigNum::from_hex_str(&data.res).or(Err(JsonErr::new(
-        StatusCode::BAD_REQUEST,
-        "res is not a number",
-    )))?;
-
-    Ok(
-        HttpResponse::Ok().json(&do_verify(client_id, &res).or(Err(JsonErr::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "unable to verify",
-        )))?),
-    )
+    observe_route("/verify", async move {
+        let client_id = client_id(&req)?;
+
+        let res = from_wire(&data.res, session_width(client_id)?, "res")?;
+
+        Ok::<_, web::Error>(HttpResponse::Ok().json(
+            &do_verify(client_id, &res, &Peer::from(&req)).or(Err(JsonErr::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to verify",
+            )))?,
+        ))
+    })
+    .await
 }
 
 pub(crate) fn routes() -> web::Scope<DefaultError> {
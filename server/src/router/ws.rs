@@ -0,0 +1,316 @@
+use crate::audit::Peer;
+use crate::router::{authenticated, handshake, pick_choice, public_key, totp, verify};
+use crate::web_utils::{from_wire, session_width, JsonErr};
+use ntex::http::error::BlockingError;
+use ntex::http::StatusCode;
+use ntex::service::{fn_factory_with_config, fn_service, Service};
+use ntex::web;
+use ntex::web::{ws, DefaultError, HttpRequest, HttpResponse};
+use pwd_dl_zkp_core::encoding;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+
+/// Messages sent by the prover, one per text frame.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum ClientMessage {
+    Handshake {
+        username: Option<String>,
+        #[serde(default)]
+        versions: Vec<String>,
+    },
+    PublicKey {
+        #[serde(with = "encoding::hex")]
+        y: Vec<u8>,
+        #[serde(default)]
+        enrollment: Option<String>,
+    },
+    Commit {
+        #[serde(with = "encoding::hex")]
+        c: Vec<u8>,
+    },
+    Response {
+        #[serde(with = "encoding::hex")]
+        res: Vec<u8>,
+    },
+    Totp {
+        code: String,
+    },
+    Authenticated,
+}
+
+/// Messages sent back by the verifier, one for every client message.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum ServerMessage {
+    Handshake(handshake::Response),
+    PublicKey,
+    Challenge(pick_choice::Response),
+    Verified(verify::Response),
+    Totp(totp::VerifyResponse),
+    Authenticated(authenticated::Response),
+    Error(JsonErr),
+}
+
+async fn handle_message(
+    client_id: &RefCell<Option<String>>,
+    peer: &Peer,
+    message: ClientMessage,
+) -> Result<ServerMessage, JsonErr> {
+    let session = || {
+        client_id.borrow().clone().ok_or(JsonErr::new(
+            StatusCode::BAD_REQUEST,
+            "handshake not performed",
+        ))
+    };
+
+    match message {
+        ClientMessage::Handshake { username, versions } => {
+            // Drawing a new group takes a while, every connection of the worker would wait.
+            let peer = peer.clone();
+            let res =
+                web::block(move || handshake::do_handshake(username.as_deref(), &versions, &peer))
+                    .await
+                    .map_err(|err| match err {
+                        BlockingError::Error(err) => err,
+                        BlockingError::Canceled => {
+                            JsonErr::new(StatusCode::INTERNAL_SERVER_ERROR, "unable to handshake")
+                        }
+                    })?;
+            *client_id.borrow_mut() = Some(res.client_id.clone());
+
+            Ok(ServerMessage::Handshake(res))
+        }
+        ClientMessage::PublicKey { y, enrollment } => {
+            let client_id = session()?;
+
+            from_wire(&y, session_width(&client_id)?, "y")?;
+
+            public_key::do_public_key(
+                &client_id,
+                &encoding::to_hex(&y),
+                enrollment.as_deref(),
+                peer,
+            )?;
+
+            Ok(ServerMessage::PublicKey)
+        }
+        ClientMessage::Commit { c } => {
+            let client_id = session()?;
+
+            from_wire(&c, session_width(&client_id)?, "c")?;
+
+            let res =
+                pick_choice::do_pick_choice(&client_id, &encoding::to_hex(&c), peer).or(Err(
+                    JsonErr::new(StatusCode::INTERNAL_SERVER_ERROR, "unable to pick a choice"),
+                ))?;
+
+            Ok(ServerMessage::Challenge(res))
+        }
+        ClientMessage::Response { res } => {
+            let client_id = session()?;
+
+            let res = from_wire(&res, session_width(&client_id)?, "res")?;
+
+            let res = verify::do_verify(&client_id, &res, peer).or(Err(JsonErr::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to verify",
+            )))?;
+
+            Ok(ServerMessage::Verified(res))
+        }
+        ClientMessage::Totp { code } => {
+            let client_id = session()?;
+
+            let res = totp::do_verify(&client_id, &code, peer)?;
+
+            Ok(ServerMessage::Totp(res))
+        }
+        ClientMessage::Authenticated => {
+            let client_id = session()?;
+
+            let res = authenticated::do_authenticated(&client_id, peer).or(Err(JsonErr::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "unable to authenticate",
+            )))?;
+
+            Ok(ServerMessage::Authenticated(res))
+        }
+    }
+}
+
+async fn handle_text(client_id: &RefCell<Option<String>>, peer: &Peer, text: &[u8]) -> ws::Message {
+    let res = match serde_json::from_slice(text) {
+        Ok(message) => handle_message(client_id, peer, message).await,
+        Err(_) => Err(JsonErr::new(StatusCode::BAD_REQUEST, "message not valid")),
+    }
+    .unwrap_or_else(ServerMessage::Error);
+
+    ws::Message::Text(serde_json::to_string(&res).unwrap().into())
+}
+
+async fn ws_service(
+    _: ws::WsSink,
+    peer: Peer,
+) -> Result<impl Service<ws::Frame, Response = Option<ws::Message>, Error = io::Error>, web::Error>
+{
+    // Every message after the handshake refers to the session opened on this connection.
+    let client_id = Rc::new(RefCell::new(None));
+
+    Ok(fn_service(move |frame| {
+        let (client_id, peer) = (client_id.clone(), peer.clone());
+
+        async move {
+            let item = match frame {
+                ws::Frame::Ping(msg) => Some(ws::Message::Pong(msg)),
+                ws::Frame::Pong(_) => None,
+                ws::Frame::Text(text) => Some(handle_text(&client_id, &peer, text.as_ref()).await),
+                ws::Frame::Close(reason) => Some(ws::Message::Close(reason)),
+                _ => Some(ws::Message::Close(None)),
+            };
+
+            Ok(item)
+        }
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/ws",
+    responses((
+        status = 101,
+        description = "The whole protocol over a websocket, one JSON message per text frame, \
+        tagged by type: handshake, publicKey, commit, response, totp and authenticated"
+    )),
+)]
+#[web::get("")]
+async fn ws_index(req: HttpRequest) -> Result<HttpResponse, web::Error> {
+    let peer = Peer::from(&req);
+
+    ws::start(
+        req,
+        fn_factory_with_config(move |sink| ws_service(sink, peer.clone())),
+    )
+    .await
+}
+
+pub(crate) fn routes() -> web::Scope<DefaultError> {
+    web::scope("/ws").service(ws_index)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{number, server, wire};
+    use ntex::io::{Io, Sealed};
+    use ntex::web::ws;
+    use ntex::ws::Codec;
+    use pwd_dl_zkp_peggy::peggy::Peggy;
+    use rand::thread_rng;
+    use serde_json::{json, Value};
+
+    async fn exchange(io: &Io<Sealed>, codec: &Codec, message: Value) -> Value {
+        io.send(ws::Message::Text(message.to_string().into()), codec)
+            .await
+            .unwrap();
+
+        match io.recv(codec).await.unwrap().unwrap() {
+            ws::Frame::Text(text) => serde_json::from_slice(&text).unwrap(),
+            frame => panic!("{:?}", frame),
+        }
+    }
+
+    /// A whole login over a single connection: registration, rounds until the server is
+    /// satisfied, then the token.
+    #[ntex::test]
+    async fn login() {
+        let srv = server();
+        let (io, codec, _) = srv.ws_at("/ws").await.unwrap().into_inner();
+
+        let res = exchange(
+            &io,
+            &codec,
+            json!({ "type": "handshake", "username": "ws" }),
+        )
+        .await;
+        assert_eq!(res["type"], "handshake");
+        let (p, g) = (number(&res["p"]), number(&res["g"]));
+        let (width, q) = (Peggy::width(&p), Peggy::order(&g, &p));
+
+        let x = Peggy::gen_r(&q, &mut thread_rng()).unwrap();
+        let y = Peggy::public_key(&x, &g, &p).unwrap();
+        let res = exchange(
+            &io,
+            &codec,
+            json!({ "type": "publicKey", "y": wire(&y, width) }),
+        )
+        .await;
+        assert_eq!(res["type"], "publicKey");
+
+        loop {
+            let r = Peggy::gen_r(&q, &mut thread_rng()).unwrap();
+            let c = Peggy::calc_c(&r, &g, &p).unwrap();
+            let res = exchange(
+                &io,
+                &codec,
+                json!({ "type": "commit", "c": wire(&c, width) }),
+            )
+            .await;
+            assert_eq!(res["type"], "challenge");
+
+            let choice = serde_json::from_value(res["choice"].clone()).unwrap();
+            let res = Peggy::calc_choice(&choice, &x, &r, &q).unwrap();
+            let res = exchange(
+                &io,
+                &codec,
+                json!({ "type": "response", "res": wire(&res, width) }),
+            )
+            .await;
+            assert_eq!(res["type"], "verified");
+            assert_eq!(res["valid"], true);
+
+            if res["cont"] == false {
+                break;
+            }
+        }
+
+        let res = exchange(&io, &codec, json!({ "type": "authenticated" })).await;
+        assert_eq!(res["type"], "authenticated");
+        assert_eq!(res["auth"], true);
+        assert!(res["token"].is_string());
+    }
+
+    /// Malformed messages are answered with an error and leave the connection open, frames
+    /// that aren't text close it.
+    #[ntex::test]
+    async fn malformed() {
+        let srv = server();
+        let (io, codec, _) = srv.ws_at("/ws").await.unwrap().into_inner();
+
+        for (message, err) in [
+            (json!("handshake"), "message not valid"),
+            (json!({ "type": "unknown" }), "message not valid"),
+            (json!({ "type": "commit", "c": "zz" }), "message not valid"),
+            (
+                json!({ "type": "commit", "c": "00" }),
+                "handshake not performed",
+            ),
+        ] {
+            let res = exchange(&io, &codec, message).await;
+            assert_eq!(res["type"], "error");
+            assert_eq!(res["err"], err);
+        }
+
+        let res = exchange(&io, &codec, json!({ "type": "handshake" })).await;
+        assert_eq!(res["type"], "handshake");
+
+        io.send(ws::Message::Binary("binary".into()), &codec)
+            .await
+            .unwrap();
+        assert!(matches!(
+            io.recv(&codec).await.unwrap().unwrap(),
+            ws::Frame::Close(None)
+        ));
+    }
+}
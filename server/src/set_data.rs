@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A named set of users that can log in anonymously, as "one of the members". Every user
+/// registered in a group of their own, so members enroll a key in the set's group.
+#[derive(Clone, Default, Debug, Deserialize, Serialize)]
+pub struct SetData {
+    pub created_at: String,
+    pub p: String,
+    pub g: String,
+    /// Allowed usernames, with the key they enrolled if any.
+    pub members: BTreeMap<String, Option<String>>,
+}
+
+impl SetData {
+    pub fn new(p: &str, g: &str) -> Self {
+        Self {
+            created_at: "".to_string(),
+            p: p.to_string(),
+            g: g.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// The enrolled keys, in the order membership proofs commit to them.
+    pub fn keys(&self) -> Vec<String> {
+        self.members.values().flatten().cloned().collect()
+    }
+}
+
+/// An anonymous membership proof, from its commitments to the verification of its response.
+#[derive(Clone, Default, Debug, Deserialize, Serialize)]
+pub struct MembershipProof {
+    pub set: String,
+    pub keys: Vec<String>,
+    pub t: Vec<String>,
+    pub challenge: String,
+    pub valid: Option<bool>,
+    /// Epoch seconds of the commitment.
+    #[serde(default)]
+    pub committed_at: u64,
+}
+
+/// Seconds a committed proof waits for its response.
+pub const PROOF_TTL_SECONDS: u64 = 300;
+
+impl MembershipProof {
+    /// Whether the proof was left unanswered, or failed, for too long to be kept. Valid ones are
+    /// kept for the tokens issued for them.
+    pub fn expired(&self, now: u64) -> bool {
+        self.valid != Some(true) && now >= self.committed_at.saturating_add(PROOF_TTL_SECONDS)
+    }
+}
@@ -0,0 +1,171 @@
+use crate::config::CONFIG;
+use crate::router::routes;
+use ntex::http::Request;
+use ntex::web::test::{
+    init_service, read_response_json, server as test_server, TestRequest, TestServer,
+};
+use ntex::web::{self, App, WebResponse};
+use ntex::Service;
+use num_bigint::BigInt;
+use num_traits::Num;
+use once_cell::sync::Lazy;
+use pwd_dl_zkp_core::core::Choice;
+use pwd_dl_zkp_core::encoding::{self, Width};
+use pwd_dl_zkp_core::protocol::Version;
+use pwd_dl_zkp_peggy::peggy::{KnowledgeProof, Peggy, Prover, Secret};
+use rand::thread_rng;
+use serde_json::{json, Value};
+use std::fmt::Debug;
+
+/// Every test binary gets a database of its own and the admin API, set before anything reads
+/// the configuration.
+static SETUP: Lazy<()> = Lazy::new(|| {
+    let db_path = std::env::temp_dir().join(format!("pwd-dl-zkp-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&db_path);
+    std::env::set_var("ZKP_DB_PATH", &db_path);
+    std::env::set_var("ZKP_ADMIN_TOKEN", ADMIN_TOKEN);
+});
+
+pub(crate) const ADMIN_TOKEN: &str = "admin";
+
+/// What the client keeps of a session: its id, the group, the order of `g` and the width of its
+/// numbers.
+pub(crate) struct Session {
+    pub client_id: String,
+    pub p: BigInt,
+    pub g: BigInt,
+    pub q: BigInt,
+    pub width: Width,
+}
+
+pub(crate) async fn app() -> impl Service<Request, Response = WebResponse, Error = impl Debug> {
+    Lazy::force(&SETUP);
+
+    init_service(
+        App::new()
+            .app_state(web::types::JsonConfig::default().limit(CONFIG.json_limit))
+            .service(routes()),
+    )
+    .await
+}
+
+/// The same app behind a real HTTP server, for clients that need one.
+pub(crate) fn server() -> TestServer {
+    Lazy::force(&SETUP);
+
+    test_server(|| {
+        App::new()
+            .app_state(web::types::JsonConfig::default().limit(CONFIG.json_limit))
+            .service(routes())
+    })
+}
+
+pub(crate) fn number(value: &Value) -> BigInt {
+    BigInt::from_str_radix(value.as_str().unwrap(), 16).unwrap()
+}
+
+pub(crate) fn wire(n: &BigInt, width: Width) -> String {
+    encoding::to_hex(&Peggy::to_wire(n, width).unwrap())
+}
+
+pub(crate) fn get(session: &Session, path: &str) -> Request {
+    TestRequest::get()
+        .uri(path)
+        .header("x-client-id", session.client_id.as_str())
+        .to_request()
+}
+
+pub(crate) fn post(session: &Session, path: &str, body: Value) -> Request {
+    TestRequest::post()
+        .uri(path)
+        .header("x-client-id", session.client_id.as_str())
+        .set_json(&body)
+        .to_request()
+}
+
+pub(crate) fn admin_post(path: &str) -> Request {
+    TestRequest::post()
+        .uri(path)
+        .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+        .to_request()
+}
+
+pub(crate) async fn handshake(
+    app: &impl Service<Request, Response = WebResponse>,
+    username: &str,
+) -> Session {
+    let res: Value = read_response_json(
+        app,
+        TestRequest::get()
+            .uri(&format!("/handshake?username={}", username))
+            .to_request(),
+    )
+    .await;
+    let p = number(&res["p"]);
+    let g = number(&res["g"]);
+
+    Session {
+        client_id: res["clientId"].as_str().unwrap().to_string(),
+        width: Peggy::width(&p),
+        q: Peggy::order(&g, &p),
+        g,
+        p,
+    }
+}
+
+/// One round of the proof for the secret `x`, returns the response of /verify.
+pub(crate) async fn round(
+    app: &impl Service<Request, Response = WebResponse>,
+    session: &Session,
+    x: &BigInt,
+) -> Value {
+    let r = Peggy::gen_r(&session.q, &mut thread_rng()).unwrap();
+    let c = Peggy::calc_c(&r, &session.g, &session.p).unwrap();
+    let c = json!({ "c": wire(&c, session.width) });
+    let res: Value = read_response_json(app, post(session, "/pick-choice", c)).await;
+    let choice: Choice = serde_json::from_value(res["choice"].clone()).unwrap();
+
+    let res = Peggy::calc_choice(&choice, x, &r, &session.q).unwrap();
+    let res = json!({ "res": wire(&res, session.width) });
+    read_response_json(app, post(session, "/verify", res)).await
+}
+
+/// Rounds with the secret `x` until the server is satisfied, then the outcome of
+/// /authenticated.
+pub(crate) async fn login(
+    app: &impl Service<Request, Response = WebResponse>,
+    session: &Session,
+    x: &BigInt,
+) -> Value {
+    while round(app, session, x).await["cont"] == true {}
+
+    read_response_json(app, get(session, "/authenticated")).await
+}
+
+/// A fresh session of `username`, registered with a new secret which is returned.
+pub(crate) async fn register(
+    app: &impl Service<Request, Response = WebResponse>,
+    username: &str,
+) -> (Session, BigInt) {
+    let session = handshake(app, username).await;
+    let x = Peggy::gen_r(&session.q, &mut thread_rng()).unwrap();
+    let y = Peggy::public_key(&x, &session.g, &session.p).unwrap();
+    let _: Value = read_response_json(
+        app,
+        post(
+            &session,
+            "/public-key",
+            json!({ "y": wire(&y, session.width) }),
+        ),
+    )
+    .await;
+
+    (session, x)
+}
+
+/// A Fiat–Shamir proof of the knowledge of `x` in the group of `g` and `p`, for `context`.
+pub(crate) fn prove_knowledge(x: &BigInt, g: &BigInt, p: &BigInt, context: &str) -> KnowledgeProof {
+    Prover::new(Version::V1, &Secret::from(x.to_bytes_be().1), g, p)
+        .unwrap()
+        .prove_knowledge(context.as_bytes(), &mut thread_rng())
+}
@@ -0,0 +1,120 @@
+use crate::client_data::ClientData;
+use crate::config::CONFIG;
+use crate::database::{DB, MEMBERSHIP_PROOFS, TOKENS, USERS};
+use crate::set_data::MembershipProof;
+use crate::user_data::UserData;
+use once_cell::sync::Lazy;
+use openssl::hash::MessageDigest;
+use openssl::memcmp;
+use openssl::pkey::{PKey, Private};
+use openssl::rand::rand_bytes;
+use openssl::sign::Signer;
+use serde_json::from_slice;
+use std::borrow::Borrow;
+
+/// Without `ZKP_TOKEN_KEY` a random key is used, and tokens don't survive a restart.
+static KEY: Lazy<PKey<Private>> = Lazy::new(|| match &CONFIG.token_key {
+    Some(key) => PKey::hmac(key.as_bytes()).unwrap(),
+    None => {
+        let mut key = [0; 32];
+        rand_bytes(&mut key).unwrap();
+
+        PKey::hmac(&key).unwrap()
+    }
+});
+
+/// Prefix of the tokens of anonymous membership proofs, it can't be mistaken for a client id.
+const MEMBERSHIP: &str = "membership";
+
+fn mac(parts: &[&str]) -> String {
+    let mut signer = Signer::new(MessageDigest::sha256(), &KEY).unwrap();
+    signer.update(parts.join(".").as_bytes()).unwrap();
+
+    signer
+        .sign_to_vec()
+        .unwrap()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn check_mac(parts: &[&str], tag: &str) -> bool {
+    let expected = mac(parts);
+
+    tag.len() == expected.len() && memcmp::eq(tag.as_bytes(), expected.as_bytes())
+}
+
+/// An authentication token, `<token id>.<transcript>.<mac>`: the id is drawn when the session
+/// authenticates and tells nothing about it, the hash of the proof transcript binds the token to
+/// the proof it was earned with.
+pub(crate) fn issue(token_id: &str, transcript: &str) -> String {
+    format!(
+        "{}.{}.{}",
+        token_id,
+        transcript,
+        mac(&[token_id, transcript])
+    )
+}
+
+/// Returns the session of a token issued by this server, as long as the session is still
+/// authenticated and, for a user, with the key they have now.
+pub(crate) fn verify(token: &str) -> Option<(String, ClientData)> {
+    let (token_id, transcript, tag) = match token.split('.').collect::<Vec<_>>().as_slice() {
+        [token_id, transcript, tag] => (*token_id, *transcript, *tag),
+        _ => return None,
+    };
+    if !check_mac(&[token_id, transcript], tag) {
+        return None;
+    }
+
+    let client_id = String::from_utf8(TOKENS.get(token_id).ok()??.to_vec()).ok()?;
+    let data: ClientData = from_slice(DB.lock().unwrap().get(&client_id).ok()??.borrow()).ok()?;
+
+    if data.auth != Some(true)
+        || data.token_id.as_deref() != Some(token_id)
+        || data.transcript != transcript
+    {
+        return None;
+    }
+
+    if let Some(username) = data.username.as_deref() {
+        let user: UserData = from_slice(USERS.get(username).ok()??.borrow()).ok()?;
+        if data.y.as_deref() != Some(user.y.as_str()) {
+            return None;
+        }
+    }
+
+    Some((client_id, data))
+}
+
+/// A token of an anonymous membership proof, `membership.<set>.<proof id>.<mac>`: it tells that
+/// the bearer owns one of the keys of `set`, and nothing else.
+pub(crate) fn issue_membership(set: &str, proof_id: &str) -> String {
+    format!(
+        "{}.{}.{}.{}",
+        MEMBERSHIP,
+        set,
+        proof_id,
+        mac(&[MEMBERSHIP, set, proof_id])
+    )
+}
+
+/// Whether `token` was issued by this server for a valid membership proof of `set`.
+pub(crate) fn verify_membership(token: &str, set: &str) -> bool {
+    let parts: Vec<&str> = token.split('.').collect();
+    let (proof_id, tag) = match parts.as_slice() {
+        [MEMBERSHIP, token_set, proof_id, tag] if *token_set == set => (*proof_id, *tag),
+        _ => return false,
+    };
+
+    if !check_mac(&[MEMBERSHIP, set, proof_id], tag) {
+        return false;
+    }
+
+    MEMBERSHIP_PROOFS
+        .get(proof_id)
+        .ok()
+        .flatten()
+        .and_then(|proof| from_slice::<MembershipProof>(proof.borrow()).ok())
+        .is_some_and(|proof| proof.set == set && proof.valid == Some(true))
+}
@@ -0,0 +1,179 @@
+use openssl::hash::MessageDigest;
+use openssl::memcmp;
+use openssl::pkey::PKey;
+use openssl::rand::rand_bytes;
+use openssl::sign::Signer;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// RFC 6238 defaults, the only parameters authenticator apps reliably support.
+const DIGITS: u32 = 6;
+const STEP: u64 = 30;
+/// Codes of the previous and of the next step are accepted too, to allow for clock drift.
+const SKEW: u64 = 1;
+const SECRET_LEN: usize = 20;
+const ISSUER: &str = "pwd-dl-zkp";
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// RFC 4648 base32 without padding, the format authenticator apps take secrets in.
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[(buffer >> bits) as usize & 31] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[(buffer << (5 - bits)) as usize & 31] as char);
+    }
+
+    encoded
+}
+
+fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = vec![];
+    let (mut buffer, mut bits) = (0u32, 0);
+
+    for c in encoded.trim_end_matches('=').bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(bytes)
+}
+
+/// RFC 4226 HOTP with HMAC-SHA1.
+fn hotp(key: &[u8], counter: u64) -> u32 {
+    let key = PKey::hmac(key).unwrap();
+    let mut signer = Signer::new(MessageDigest::sha1(), &key).unwrap();
+    signer.update(&counter.to_be_bytes()).unwrap();
+    let hmac = signer.sign_to_vec().unwrap();
+
+    let offset = (hmac[hmac.len() - 1] & 0xf) as usize;
+    let code = u32::from_be_bytes([
+        hmac[offset] & 0x7f,
+        hmac[offset + 1],
+        hmac[offset + 2],
+        hmac[offset + 3],
+    ]);
+
+    code % 10u32.pow(DIGITS)
+}
+
+/// Seconds since the epoch, the time TOTP steps are counted from.
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// A new random secret, base32 encoded.
+pub(crate) fn generate_secret() -> String {
+    let mut secret = [0; SECRET_LEN];
+    rand_bytes(&mut secret).unwrap();
+
+    base32_encode(&secret)
+}
+
+/// The `otpauth://` URI authenticator apps enroll from, usually shown as a QR code.
+pub(crate) fn uri(username: &str, secret: &str) -> String {
+    let label: String = username
+        .bytes()
+        .map(|b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+
+    format!(
+        "otpauth://totp/{issuer}:{label}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={digits}&period={period}",
+        issuer = ISSUER,
+        label = label,
+        secret = secret,
+        digits = DIGITS,
+        period = STEP,
+    )
+}
+
+/// Checks `code` against the steps around `now`, returning the step it belongs to. Steps up to
+/// `last_step` are refused, so that a code can't be used twice.
+pub(crate) fn verify(secret: &str, code: &str, now: u64, last_step: Option<u64>) -> Option<u64> {
+    let key = base32_decode(secret)?;
+    let step = now / STEP;
+
+    (step.saturating_sub(SKEW)..=step + SKEW)
+        .filter(|candidate| last_step.is_none_or(|last_step| *candidate > last_step))
+        .find(|candidate| {
+            let expected = format!(
+                "{:0width$}",
+                hotp(&key, *candidate),
+                width = DIGITS as usize
+            );
+
+            code.len() == expected.len() && memcmp::eq(code.as_bytes(), expected.as_bytes())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base32_decode, base32_encode, uri, verify, STEP};
+
+    const SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn base32() {
+        let encoded = base32_encode(SECRET);
+
+        assert_eq!(encoded, "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        assert_eq!(base32_decode(&encoded).unwrap(), SECRET);
+        assert_eq!(base32_decode("gezdgnbv").unwrap(), b"12345");
+        assert!(base32_decode("GEZ1").is_none());
+    }
+
+    /// The SHA1 vectors of RFC 6238 appendix B, truncated to 6 digits.
+    #[test]
+    fn rfc_6238() {
+        let secret = base32_encode(SECRET);
+
+        for (time, code) in [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ] {
+            assert_eq!(verify(&secret, code, time, None), Some(time / STEP));
+        }
+
+        assert_eq!(verify(&secret, "287082", 59 + STEP, None), Some(1));
+        assert_eq!(verify(&secret, "287082", 59 + 2 * STEP, None), None);
+        assert_eq!(verify(&secret, "287082", 59, Some(1)), None);
+        assert_eq!(verify(&secret, "287083", 59, None), None);
+    }
+
+    #[test]
+    fn otpauth_uri() {
+        assert_eq!(
+            uri("jane doe", "GEZDGNBV"),
+            "otpauth://totp/pwd-dl-zkp:jane%20doe?secret=GEZDGNBV&issuer=pwd-dl-zkp&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
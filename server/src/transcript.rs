@@ -0,0 +1,61 @@
+use crate::web_utils::{to_wire, width_of};
+use openssl::bn::BigNumRef;
+use openssl::sha::Sha256;
+use pwd_dl_zkp_core::core::Choice;
+use pwd_dl_zkp_core::encoding::{self, Width};
+use pwd_dl_zkp_core::protocol::Version;
+
+/// A running SHA-256 over everything a session committed to: every step hashes the previous
+/// digest, a label and the step's fields, each length prefixed, numbers in the canonical
+/// encoding of the group. The digest is kept hex encoded in the session data.
+fn absorb(transcript: &str, label: &str, fields: &[&[u8]]) -> String {
+    let mut hasher = Sha256::new();
+
+    for field in [transcript.as_bytes(), label.as_bytes()]
+        .iter()
+        .chain(fields)
+    {
+        hasher.update(&(field.len() as u32).to_be_bytes());
+        hasher.update(field);
+    }
+
+    encoding::to_hex(&hasher.finish())
+}
+
+pub(crate) fn start(version: Version, p: &BigNumRef, g: &BigNumRef) -> String {
+    let width = width_of(p);
+
+    absorb(
+        "",
+        "handshake",
+        &[
+            version.as_str().as_bytes(),
+            &to_wire(p, width),
+            &to_wire(g, width),
+        ],
+    )
+}
+
+pub(crate) fn public_key(transcript: &str, width: Width, y: &BigNumRef) -> String {
+    absorb(transcript, "public-key", &[&to_wire(y, width)])
+}
+
+pub(crate) fn round(
+    transcript: &str,
+    width: Width,
+    c: &BigNumRef,
+    choice: &Choice,
+    res: &BigNumRef,
+    valid: bool,
+) -> String {
+    absorb(
+        transcript,
+        "round",
+        &[
+            &to_wire(c, width),
+            format!("{:?}", choice).as_bytes(),
+            &to_wire(res, width),
+            &[valid as u8],
+        ],
+    )
+}
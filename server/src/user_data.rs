@@ -0,0 +1,71 @@
+use openssl::memcmp;
+use openssl::sha::sha256;
+use pwd_dl_zkp_core::protocol::Version;
+use serde::{Deserialize, Serialize};
+
+/// A TOTP secret, only asked for at login once a code has confirmed the enrollment.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Totp {
+    /// Base32 encoded, as shown to authenticator apps.
+    pub secret: String,
+    pub confirmed: bool,
+    /// The step of the last accepted code, codes can't be used twice.
+    pub last_step: Option<u64>,
+    /// Wrong codes sent at login since the last valid one, whatever the session.
+    #[serde(default)]
+    pub failed_attempts: u32,
+}
+
+#[derive(Clone, Default, Debug, Deserialize, Serialize)]
+pub struct UserData {
+    pub created_at: String,
+    /// The version the key was registered with, logins must speak it too.
+    #[serde(default)]
+    pub version: Version,
+    pub p: String,
+    pub g: String,
+    pub y: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp: Option<Totp>,
+    #[serde(default)]
+    pub locked: bool,
+    /// Set by support staff: the hex sha256 of the one-time code handed out to the user, the
+    /// next registration presenting it replaces `y`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enrollment: Option<String>,
+}
+
+impl UserData {
+    pub fn new(version: Version, p: &str, g: &str, y: &str) -> Self {
+        Self {
+            created_at: "".to_string(),
+            version,
+            p: p.to_string(),
+            g: g.to_string(),
+            y: y.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether `code` is the one-time code of a pending re-enrollment.
+    pub fn enrollment_matches(&self, code: &str) -> bool {
+        let digest = enrollment_digest(code);
+
+        self.enrollment.as_ref().is_some_and(|expected| {
+            expected.len() == digest.len() && memcmp::eq(expected.as_bytes(), digest.as_bytes())
+        })
+    }
+
+    /// Whether logins need a TOTP code on top of the proof of the password.
+    pub fn totp_required(&self) -> bool {
+        self.totp.as_ref().is_some_and(|totp| totp.confirmed)
+    }
+}
+
+/// What is stored of a one-time enrollment code.
+pub fn enrollment_digest(code: &str) -> String {
+    sha256(code.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
@@ -1,9 +1,17 @@
+use crate::client_data::ClientData;
+use crate::database::DB;
 use ntex::http::{Response, StatusCode};
 use ntex::web::{HttpRequest, HttpResponse, WebResponseError};
+use openssl::bn::{BigNum, BigNumRef};
+use pwd_dl_zkp_core::encoding::{self, Width};
 use serde::Serialize;
+use serde_json::from_slice;
+use std::borrow::Borrow;
 use std::fmt::{Display, Formatter};
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = ErrorResponse)]
 pub(crate) struct JsonErr {
     err: String,
     #[serde(skip_serializing)]
@@ -35,6 +43,74 @@ impl WebResponseError for JsonErr {
     }
 }
 
+/// The token of an `authorization: Bearer <token>` header.
+pub(crate) fn bearer_token(req: &HttpRequest) -> Result<&str, JsonErr> {
+    req.headers()
+        .get("authorization")
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .ok_or(JsonErr::new(
+            StatusCode::UNAUTHORIZED,
+            "authorization header not present or not valid",
+        ))
+}
+
+/// The session of an `x-client-id` header.
+pub(crate) fn client_id(req: &HttpRequest) -> Result<&str, JsonErr> {
+    req.headers()
+        .get("x-client-id")
+        .and_then(|header| header.to_str().ok())
+        .ok_or(JsonErr::new(
+            StatusCode::BAD_REQUEST,
+            "x-client-id header not present or not valid",
+        ))
+}
+
+/// The width numbers of the group of `p` are encoded with.
+pub(crate) fn width_of(p: &BigNumRef) -> Width {
+    Width::of_modulus(&p.to_vec())
+}
+
+/// `n` in the canonical encoding of its group.
+pub(crate) fn to_wire(n: &BigNumRef, width: Width) -> Vec<u8> {
+    n.to_vec_padded(width.0 as i32).unwrap()
+}
+
+/// `n` hex encoded as it is stored, the canonical encoding too.
+pub(crate) fn to_stored(n: &BigNumRef, width: Width) -> String {
+    encoding::to_hex(&to_wire(n, width))
+}
+
+/// A number stored hex encoded, `width` wide, in the canonical encoding. Numbers stored before
+/// the encoding was canonical are accepted in any case and width.
+pub(crate) fn stored_to_wire(n: &str, width: Width) -> Vec<u8> {
+    to_wire(&BigNum::from_hex_str(n).unwrap(), width)
+}
+
+/// The width of the numbers of the session `client_id`, to check them before it is locked.
+pub(crate) fn session_width(client_id: &str) -> Result<Width, JsonErr> {
+    let data: Option<ClientData> = DB
+        .lock()
+        .unwrap()
+        .get(client_id)
+        .unwrap()
+        .map(|data| from_slice(data.borrow()).unwrap());
+
+    data.and_then(|data| data.p)
+        .map(|p| width_of(&BigNum::from_hex_str(&p).unwrap()))
+        .ok_or(JsonErr::new(StatusCode::NOT_FOUND, "no such session"))
+}
+
+/// A number received in the canonical encoding of its group, named `name` in errors.
+pub(crate) fn from_wire(bytes: &[u8], width: Width, name: &str) -> Result<BigNum, JsonErr> {
+    width.check(bytes).or(Err(JsonErr::new(
+        StatusCode::BAD_REQUEST,
+        &format!("{} is not a number of the group", name),
+    )))?;
+
+    Ok(BigNum::from_slice(bytes).unwrap())
+}
+
 #[derive(Debug, PartialEq)]
 pub(crate) struct DbTxErr {
     err: String,
@@ -18,7 +18,7 @@ export default function Authenticate({ clientId, g, p, setAuthenticated, x, zkpL
   const authenticate = useCallback(() => {
     setBtnEnabled(false)
 
-    const r = zkpLib.gen_r(p)
+    const r = zkpLib.gen_r(g, p)
 
     const c = zkpLib.calc_c(r, g, p)
 
@@ -46,7 +46,7 @@ export default function Authenticate({ clientId, g, p, setAuthenticated, x, zkpL
     async function verify(choice: string) {
       const res = await fetch('http://localhost:8000/verify', {
         cache: 'no-cache',
-        body: JSON.stringify({ res: zkpLib.calc_choice(choice, x, r, p) }),
+        body: JSON.stringify({ res: zkpLib.calc_choice(choice, x, r, g, p) }),
         headers: {
           'content-type': 'application/json',
           'x-client-id': clientId,
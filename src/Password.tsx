@@ -1,5 +1,8 @@
 import React, { useCallback, useState } from 'react'
 
+/** Protocol versions this page speaks, most preferred first. */
+const PROTOCOL_VERSIONS = ['pwd-dl-zkp/2']
+
 function encodeStringToBigInt(s: string): string {
   return s
     .split('')
@@ -35,18 +38,23 @@ export default function Password({ setClientId, setG, setP, setX, x, zkpLib }: P
       const { signal } = controller
 
       async function handshake() {
-        const res = await fetch('http://localhost:8000/handshake', {
+        const versions = encodeURIComponent(PROTOCOL_VERSIONS.join(','))
+        const res = await fetch(`http://localhost:8000/handshake?versions=${versions}`, {
           cache: 'no-cache',
           mode: 'cors',
           signal,
         })
 
-        const { clientId, p, g } = await res.json()
+        const { clientId, version, p, g } = await res.json()
 
         if (!clientId) {
           throw new Error('No client id returned.')
         }
 
+        if (!PROTOCOL_VERSIONS.includes(version)) {
+          throw new Error(`Unsupported protocol version ${version}.`)
+        }
+
         setClientId(clientId)
         setP(p)
         setG(g)